@@ -1,4 +1,4 @@
-use crate::disp_utils::hm_from_duration;
+use crate::disp_utils::{hm_from_duration, signed_hm_from_duration};
 use crate::editor::{EditDayType, EditField, EditMode};
 use ratatui::{
     prelude::{Color, Frame, Rect, Style, Stylize},
@@ -10,5 +10,5 @@ pub use crate::app_common::*;
 pub use crate::app_common::colors::*;
 pub use crate::app_common::SCROLL_AMT;
 
-pub mod render;
 pub mod events;
+pub mod render;
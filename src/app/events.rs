@@ -1,10 +1,13 @@
-use chrono::{Local, NaiveTime};
+use chrono::{Datelike, Local, NaiveTime};
 use crossterm::event::{self, Event, KeyCode, KeyModifiers};
 
 use crate::app_common::{AppMode, AppState, Message, StatsState};
+use crate::balance::running_balance;
 use crate::editor::{EditBufs, EditField, EditMode};
-use crate::stat::{total_stats, weekly_stats};
-use crate::work_day::{Break, DayType, WorkDay};
+use crate::history::Change;
+use crate::stat::{period_stats, total_stats, Period};
+use crate::vacation::vacation_year;
+use crate::work_day::{DayType, Interval, WorkDay};
 
 pub fn handle_events(state: &mut AppState) -> Result<bool, ()> {
     if event::poll(std::time::Duration::from_millis(50)).map_err(|err| {
@@ -56,6 +59,29 @@ fn handle_events_help(state: &mut AppState) -> Result<bool, ()> {
     Ok(false)
 }
 
+/// Statistics of all days grouped by `period`, `None` if there are no finished days
+fn statistics(state: &AppState, period: Period) -> Option<StatsState> {
+    let mut total = total_stats(&state.days, &state.schedule, None, None)?;
+    let mut periods = period_stats(&state.days, &state.schedule, period, None, None);
+    total.balance = running_balance(&mut periods, period, &state.bookings);
+    let mut bookings = state.bookings.clone();
+    bookings.sort_by_key(|booking| booking.date);
+    let today = Local::now().date_naive();
+    let vacation = state
+        .settings
+        .vacation
+        .as_ref()
+        .map(|entitlement| vacation_year(&state.days, entitlement, today.year(), today));
+    Some(StatsState {
+        period,
+        periods,
+        total,
+        bookings,
+        vacation,
+        scroll: 0,
+    })
+}
+
 pub fn handle_events_stats(state: &mut AppState) -> Result<bool, ()> {
     if let Event::Key(key) = event::read().map_err(|err| {
         eprintln!("Could not read event: {err}");
@@ -64,6 +90,11 @@ pub fn handle_events_stats(state: &mut AppState) -> Result<bool, ()> {
             match key.code {
                 KeyCode::Char('q') => return Ok(true),
                 KeyCode::Char('s') | KeyCode::Esc => state.statistics = None,
+                KeyCode::Char('p') => {
+                    if let Some(period) = state.statistics.as_ref().map(|stats| stats.period) {
+                        state.statistics = statistics(state, period.next());
+                    }
+                }
                 KeyCode::Char('j') => {
                     state
                         .statistics
@@ -125,10 +156,18 @@ fn handle_events_listonly(state: &mut AppState) -> Result<bool, ()> {
             match key.code {
                 KeyCode::Char('?') => state.help_popup = Some(0),
                 KeyCode::Char('q') => return Ok(true),
-                KeyCode::Char('w') => state.write()?,
-                KeyCode::Char('x') => {
-                    state.write()?;
-                    return Ok(true);
+                KeyCode::Char('w') => {
+                    state.write();
+                }
+                KeyCode::Char('x') => return Ok(state.write()),
+                KeyCode::Char('R') if matches!(state.message, Message::Conflict(_)) => {
+                    state.reload();
+                }
+                KeyCode::Char('M') if matches!(state.message, Message::Conflict(_)) => {
+                    state.merge();
+                }
+                KeyCode::Char('W') if matches!(state.message, Message::Conflict(_)) => {
+                    state.overwrite();
                 }
                 KeyCode::Char('d') if key.modifiers == KeyModifiers::CONTROL => {
                     for _ in 0..5 {
@@ -140,13 +179,15 @@ fn handle_events_listonly(state: &mut AppState) -> Result<bool, ()> {
                         state.selected = state.prev_day();
                     }
                 }
+                KeyCode::Char('u') => state.undo(),
+                KeyCode::Char('r') if key.modifiers == KeyModifiers::CONTROL => state.redo(),
                 KeyCode::Char('d') => {
-                    if let Some(selected) = state.selected.as_mut() {
-                        let removed = state.days.remove(*selected);
-                        if *selected == state.days.len() {
-                            *selected = state.days.len() - 1;
-                        }
-                        state.message = Message::Info(format!("Removed entry of {}", removed.date))
+                    if let Some(selected) = state.selected {
+                        let removed = state.days.remove(selected);
+                        state.days_changed();
+                        state.select(selected);
+                        state.message = Message::Info(format!("Removed entry of {}", removed.date));
+                        state.history.record(Change::Remove(removed));
                     }
                 }
                 KeyCode::Char('j') => state.selected = state.next_day(),
@@ -161,33 +202,25 @@ fn handle_events_listonly(state: &mut AppState) -> Result<bool, ()> {
                         }
                     }
                 }
-                KeyCode::Char('s') => {
-                    state.statistics = {
-                        if let Some(total) = total_stats(&state.days) {
-                            Some(StatsState {
-                                weekly: weekly_stats(&state.days),
-                                total,
-                                week_hours: state.settings.week_hours,
-                                scroll: 0,
-                            })
-                        } else {
-                            None
-                        }
-                    }
-                }
+                KeyCode::Char('s') => state.statistics = statistics(state, Period::Week),
                 KeyCode::Char('+') | KeyCode::Char('a') => {
-                    state.days.push(WorkDay {
-                        date: Local::now().naive_local().date(),
-                        day_type: DayType::Present {
-                            start: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
-                            end: NaiveTime::from_hms_opt(16, 30, 0).unwrap(),
-                            brk: Break {
-                                start: NaiveTime::from_hms_opt(11, 30, 0).unwrap(),
-                                end: NaiveTime::from_hms_opt(12, 00, 0).unwrap(),
-                            },
+                    state.days.push(WorkDay::new(
+                        Local::now().naive_local().date(),
+                        DayType::Present {
+                            sessions: vec![Interval {
+                                start: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+                                end: NaiveTime::from_hms_opt(16, 30, 0),
+                                next_day: false,
+                                start_next_day: false,
+                            }],
+                            breaks: state.settings.default_break.into_iter().collect(),
                         },
-                    });
+                    ));
+                    state.days_changed();
                     let selected = state.days.len() - 1;
+                    state
+                        .history
+                        .record(Change::Add(state.days[selected].clone()));
                     state.selected = Some(selected);
                     state.mode = AppMode::Edit {
                         mode: EditMode::Move,
@@ -230,20 +263,24 @@ fn handle_events_edit(state: &mut AppState) -> Result<bool, ()> {
                 EditMode::Move => match key.code {
                     KeyCode::Char('?') => state.help_popup = Some(0),
                     KeyCode::Char('q') => return Ok(true),
-                    KeyCode::Char('w') => state.write()?,
+                    KeyCode::Char('w') => {
+                        state.write();
+                    }
                     KeyCode::Char('s') => match (&*edit_bufs).try_into() {
                         Ok(val) => {
-                            state.days[*selected] = val;
+                            let before = std::mem::replace(&mut state.days[*selected], val);
+                            if before != state.days[*selected] {
+                                let after = state.days[*selected].clone();
+                                state.history.record(Change::Edit { before, after });
+                            }
                             state.message =
                                 Message::Info(String::from("WorkDay parsed successfully"));
                             state.days.sort_by_key(|day| day.date);
+                            state.days_changed();
                         }
                         Err(err) => state.message = Message::Error(err),
                     },
-                    KeyCode::Char('x') => {
-                        state.write()?;
-                        return Ok(true);
-                    }
+                    KeyCode::Char('x') => return Ok(state.write()),
                     KeyCode::Tab => {
                         *selected = next.unwrap();
                         state.mode = AppMode::Edit {
@@ -262,16 +299,69 @@ fn handle_events_edit(state: &mut AppState) -> Result<bool, ()> {
                             index: *selected,
                         }
                     }
-                    KeyCode::Char('j') => *field = field.next(edit_bufs.day_type),
-                    KeyCode::Char('k') => *field = field.prev(edit_bufs.day_type),
+                    KeyCode::Char('j') => *field = field.next(edit_bufs),
+                    KeyCode::Char('k') => *field = field.prev(edit_bufs),
+                    KeyCode::Char('n') if edit_bufs.day_type.has_sessions() => {
+                        *field = EditField::Start(edit_bufs.add_session());
+                    }
+                    KeyCode::Char('N')
+                        if edit_bufs.day_type.has_sessions() && edit_bufs.num_sessions() > 0 =>
+                    {
+                        let removed = match *field {
+                            EditField::Start(i) | EditField::End(i) => {
+                                *field = EditField::Start(i).prev(edit_bufs);
+                                i
+                            }
+                            _ => edit_bufs.num_sessions() - 1,
+                        };
+                        edit_bufs.remove_session(removed);
+                        state.message =
+                            Message::Info(format!("Removed work session {}", removed + 1));
+                    }
+                    KeyCode::Char('b') if edit_bufs.day_type.has_breaks() => {
+                        *field = EditField::BreakStart(edit_bufs.add_break());
+                    }
+                    KeyCode::Char('B')
+                        if edit_bufs.day_type.has_breaks() && edit_bufs.num_breaks() > 0 =>
+                    {
+                        let removed = match *field {
+                            EditField::BreakStart(i) | EditField::BreakEnd(i) => {
+                                *field = EditField::BreakStart(i).prev(edit_bufs);
+                                i
+                            }
+                            _ => edit_bufs.num_breaks() - 1,
+                        };
+                        edit_bufs.remove_break(removed);
+                        state.message = Message::Info(format!("Removed break {}", removed + 1));
+                    }
+                    KeyCode::Char('p') if edit_bufs.day_type.has_sessions() => {
+                        *field = EditField::Project(edit_bufs.add_allocation());
+                    }
+                    KeyCode::Char('P')
+                        if edit_bufs.day_type.has_sessions() && edit_bufs.num_allocations() > 0 =>
+                    {
+                        let removed = match *field {
+                            EditField::Project(i) | EditField::Allocation(i) => {
+                                *field = EditField::Project(i).prev(edit_bufs);
+                                i
+                            }
+                            _ => edit_bufs.num_allocations() - 1,
+                        };
+                        edit_bufs.remove_allocation(removed);
+                        state.message =
+                            Message::Info(format!("Removed project allocation {}", removed + 1));
+                    }
                     KeyCode::Esc | KeyCode::Char('h') => state.mode = AppMode::ListOnly,
-                    KeyCode::Enter | KeyCode::Char('l') => {
-                        if *field == EditField::DayType {
+                    KeyCode::Enter | KeyCode::Char('l') => match *field {
+                        EditField::DayType => {
                             edit_bufs.day_type = edit_bufs.day_type.next();
-                        } else {
-                            *e_mode = EditMode::Insert;
+                            if edit_bufs.day_type.has_sessions() && edit_bufs.num_sessions() == 0 {
+                                edit_bufs.add_session();
+                            }
                         }
-                    }
+                        EditField::Absence => edit_bufs.cycle_absence(),
+                        _ => *e_mode = EditMode::Insert,
+                    },
                     _ => (),
                 },
                 EditMode::Insert => match key.code {
@@ -280,20 +370,11 @@ fn handle_events_edit(state: &mut AppState) -> Result<bool, ()> {
                     }
                     KeyCode::Char(c) => {
                         assert_ne!(*field, EditField::DayType);
-                        if c.is_ascii() {
-                            let (buf, cur) = edit_bufs.entry_mut(*field);
-                            if *cur < 64 {
-                                buf[*cur as usize] = c as u8;
-                                *cur += 1;
-                            }
-                        }
+                        edit_bufs.entry_mut(*field).push(c);
                     }
                     KeyCode::Backspace => {
                         assert_ne!(*field, EditField::DayType);
-                        let (_, cur) = edit_bufs.entry_mut(*field);
-                        if *cur > 0 {
-                            *cur -= 1;
-                        }
+                        edit_bufs.entry_mut(*field).pop();
                     }
                     _ => (),
                 },
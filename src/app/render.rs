@@ -4,6 +4,8 @@ use ratatui::widgets::{Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarStat
 use static_assertions::const_assert_eq;
 
 use crate::app_common::Message;
+use crate::stat::Period;
+use crate::work_day::AbsenceKind;
 
 use super::*;
 
@@ -40,7 +42,12 @@ pub fn render_application(frame: &mut Frame, state: &AppState) {
         popup_area.width -= stat_inset * 2;
         popup_area.height -= stat_inset * 2;
 
-        render_statistics_popup(frame, &popup_area, state.statistics.as_ref().unwrap());
+        render_statistics_popup(
+            frame,
+            &popup_area,
+            state.statistics.as_ref().unwrap(),
+            &state.settings,
+        );
     }
 
     if state.help_popup.is_some() {
@@ -56,6 +63,7 @@ pub fn render_application(frame: &mut Frame, state: &AppState) {
 }
 
 fn render_edit_window(frame: &mut Frame, pos: &Rect, state: &AppState) {
+    let colors = &state.settings.colors;
     if let AppMode::Edit {
         field,
         edit_bufs,
@@ -66,66 +74,59 @@ fn render_edit_window(frame: &mut Frame, pos: &Rect, state: &AppState) {
         let day = &state.days[*index];
 
         use EditField::*;
-        let field_index = match *field {
-            Date => 0,
-            DayType => 1,
-            Start => 2,
-            End => 3,
-            BreakStart => 4,
-            BreakEnd => 5,
-        };
+        let fields = edit_bufs.fields();
+        let field_index = fields.iter().position(|f| f == field).unwrap_or(0);
 
-        let mut names = vec![ListItem::new("Date"), ListItem::new("Status")];
-        let mut bufs = vec![
-            edit_bufs.text(Date),
-            match edit_bufs.day_type {
-                EditDayType::Present => "Present",
-                EditDayType::HomeOffice => "Home Office",
-                EditDayType::Sick => "Sick",
-                EditDayType::Vacation => "Vacation",
-                EditDayType::Travel => "Travel",
-                EditDayType::Unofficial { has_break } => {
-                    if has_break {
-                        "Unofficial (with break)"
-                    } else {
-                        "Unofficial (no break)"
-                    }
-                }
-            },
-        ];
-
-        match edit_bufs.day_type {
-            EditDayType::Present | EditDayType::HomeOffice => {
-                names.extend_from_slice(&[
-                    ListItem::new("Start"),
-                    ListItem::new("End"),
-                    ListItem::new("Break Start"),
-                    ListItem::new("Break End"),
-                ]);
-                bufs.extend_from_slice(&[
-                    edit_bufs.text(Start),
-                    edit_bufs.text(End),
-                    edit_bufs.text(BreakStart),
-                    edit_bufs.text(BreakEnd),
-                ]);
-            }
-            EditDayType::Sick | EditDayType::Vacation => (),
-            EditDayType::Unofficial { has_break } => {
-                names.extend_from_slice(&[ListItem::new("Start"), ListItem::new("End")]);
-                bufs.extend_from_slice(&[edit_bufs.text(Start), edit_bufs.text(End)]);
-                if has_break {
-                    bufs.extend_from_slice(&[edit_bufs.text(BreakStart), edit_bufs.text(BreakEnd)]);
-                    names.extend_from_slice(&[
-                        ListItem::new("Break Start"),
-                        ListItem::new("Break End"),
-                    ]);
-                }
-            }
-            EditDayType::Travel => {
-                names.extend_from_slice(&[ListItem::new("Start"), ListItem::new("End")]);
-                bufs.extend_from_slice(&[edit_bufs.text(Start), edit_bufs.text(End)]);
+        let session_name = |name: &str, i: usize| {
+            if edit_bufs.num_sessions() > 1 {
+                format!("{name} {}", i + 1)
+            } else {
+                String::from(name)
             }
-        }
+        };
+
+        let names = fields
+            .iter()
+            .map(|f| {
+                ListItem::new(match *f {
+                    Date => String::from("Date"),
+                    DayType => String::from("Status"),
+                    HolidayName => String::from("Holiday"),
+                    Start(i) => session_name("Start", i),
+                    End(i) => session_name("End", i),
+                    BreakStart(i) => format!("Break {} Start", i + 1),
+                    BreakEnd(i) => format!("Break {} End", i + 1),
+                    Absence => String::from("Absence"),
+                    AbsenceShare => String::from("Absence Share"),
+                    Project(i) => format!("Project {}", i + 1),
+                    Allocation(i) => format!("Project {} Time", i + 1),
+                    Note => String::from("Note"),
+                    Tags => String::from("Tags"),
+                })
+            })
+            .collect::<Vec<_>>();
+        let bufs = fields
+            .iter()
+            .map(|f| match *f {
+                DayType => match edit_bufs.day_type {
+                    EditDayType::Present => "Present",
+                    EditDayType::HomeOffice => "Home Office",
+                    EditDayType::Sick => "Sick",
+                    EditDayType::Vacation => "Vacation",
+                    EditDayType::Compensation => "Compensation",
+                    EditDayType::Travel => "Travel",
+                    EditDayType::Unofficial => "Unofficial",
+                    EditDayType::Holiday => "Holiday",
+                },
+                Absence => match edit_bufs.absence {
+                    None => "None",
+                    Some(AbsenceKind::Vacation) => "Vacation",
+                    Some(AbsenceKind::Compensation) => "Compensation",
+                    Some(AbsenceKind::Sick) => "Sick",
+                },
+                f => edit_bufs.text(f),
+            })
+            .collect::<Vec<_>>();
 
         let bufs = bufs
             .iter()
@@ -161,16 +162,16 @@ fn render_edit_window(frame: &mut Frame, pos: &Rect, state: &AppState) {
 
         frame.render_stateful_widget(
             List::new(names).highlight_style(Style::default().bold().fg(match e_mode {
-                EditMode::Move => EDIT_MOVE_CLR,
-                EditMode::Insert => EDIT_INS_CLR,
+                EditMode::Move => colors.edit_move,
+                EditMode::Insert => colors.edit_insert,
             })),
             name_area,
             &mut ListState::default().with_selected(Some(field_index)),
         );
         frame.render_stateful_widget(
             List::new(bufs).highlight_style(Style::default().bold().fg(match e_mode {
-                EditMode::Move => EDIT_MOVE_CLR,
-                EditMode::Insert => EDIT_INS_CLR,
+                EditMode::Move => colors.edit_move,
+                EditMode::Insert => colors.edit_insert,
             })),
             buf_area,
             &mut ListState::default().with_selected(Some(field_index)),
@@ -181,13 +182,14 @@ fn render_edit_window(frame: &mut Frame, pos: &Rect, state: &AppState) {
 }
 
 fn render_list(frame: &mut Frame, pos: &Rect, state: &AppState, active: bool) {
+    let colors = &state.settings.colors;
     frame.render_widget(Clear, *pos);
     frame.render_widget(
         Block::default()
             .title("Work Days")
             .borders(Borders::ALL)
             .border_style(if active {
-                Style::default().fg(MOVE_CLR)
+                Style::default().fg(colors.accent)
             } else {
                 Style::default().fg(Color::Gray)
             }),
@@ -205,11 +207,28 @@ fn render_list(frame: &mut Frame, pos: &Rect, state: &AppState, active: bool) {
             state
                 .days
                 .iter()
-                .map(|day| ListItem::new(day.to_string()))
+                .enumerate()
+                .map(|(index, day)| {
+                    // running days have no final surplus yet
+                    let surplus = if day.is_running() {
+                        String::new()
+                    } else {
+                        signed_hm_from_duration(state.schedule.day_surplus(day))
+                    };
+                    let line =
+                        format!("{surplus:>6}  {}", day.display(&state.settings.date_format));
+                    match state.violations.get(&index) {
+                        Some(violations) => {
+                            ListItem::new(format!("{line}  ! {}", violations.join(", ")))
+                                .style(Style::default().fg(Color::LightRed))
+                        }
+                        None => ListItem::new(line),
+                    }
+                })
                 .collect::<Vec<_>>(),
         )
         .highlight_symbol("> ")
-        .highlight_style(Style::default().fg(MOVE_CLR).bold()),
+        .highlight_style(Style::default().fg(colors.accent).bold()),
         inner_area,
         &mut ListState::default().with_selected(state.selected),
     );
@@ -235,7 +254,7 @@ fn render_message_area(frame: &mut Frame, area: &Rect, message: &Message) {
                 .style(Style::default().fg(Color::LightBlue).bold()),
             *area,
         ),
-        Message::Error(msg) => frame.render_widget(
+        Message::Error(msg) | Message::Conflict(msg) => frame.render_widget(
             Block::default()
                 .title(msg.clone())
                 .style(Style::default().fg(Color::LightYellow).bold()),
@@ -246,12 +265,13 @@ fn render_message_area(frame: &mut Frame, area: &Rect, message: &Message) {
 }
 
 pub fn render_help_popup(frame: &mut Frame, area: &Rect, state: &AppState) {
+    let colors = &state.settings.colors;
     frame.render_widget(Clear, *area);
     frame.render_widget(
         Block::default()
             .title("Help")
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(HELP_CLR).bold()),
+            .border_style(Style::default().fg(colors.help).bold()),
         *area,
     );
 
@@ -271,10 +291,13 @@ pub fn render_help_popup(frame: &mut Frame, area: &Rect, state: &AppState) {
         "            w  write to disk",
         "            x  write to disk and quit",
         "            d  delete current selection",
+        "      u/<c-r>  undo / redo add, delete and edit",
         "          j/k  scroll down/up",
         "      <c-u/d>  scroll down/up by 5",
         "    l/<enter>  enter edit mode - move on selection",
         "          +/a  add new entry",
+        "            s  show statistics",
+        "        R/M/W  reload / merge / overwrite after a change on disk",
     ]
     .as_slice();
 
@@ -289,6 +312,13 @@ pub fn render_help_popup(frame: &mut Frame, area: &Rect, state: &AppState) {
         "          j/k  field below/above",
         "      <esc>/h  go back to list mode",
         "    <enter>/l  edit current field (edit mode - insert)",
+        "               or cycle status / absence",
+        "            n  add work session",
+        "            N  remove selected (or last) work session",
+        "            b  add break",
+        "            B  remove selected (or last) break",
+        "            p  add project allocation",
+        "            P  remove selected (or last) project allocation",
     ]
     .as_slice();
 
@@ -309,11 +339,21 @@ pub fn render_help_popup(frame: &mut Frame, area: &Rect, state: &AppState) {
     ]
     .as_slice();
 
+    let stats_text = [
+        "            q  quit",
+        "      s/<esc>  close statistics",
+        "          j/k  scroll down/up",
+        "      <c-u/d>  scroll down/up by 5",
+        "            p  show weeks / months / years",
+    ]
+    .as_slice();
+
     let help_segments = [
-        (list_text, MOVE_CLR, "Move Mode"),
-        (edit_move_text, EDIT_MOVE_CLR, "Edit Mode - Move"),
-        (edit_insert_text, EDIT_INS_CLR, "Edit Mode - Insert"),
-        (help_text, HELP_CLR, "Help Popup"),
+        (list_text, colors.accent, "Move Mode"),
+        (edit_move_text, colors.edit_move, "Edit Mode - Move"),
+        (edit_insert_text, colors.edit_insert, "Edit Mode - Insert"),
+        (help_text, colors.help, "Help Popup"),
+        (stats_text, colors.stats, "Statistics Popup"),
     ];
 
     let mut lines = Vec::new();
@@ -356,51 +396,72 @@ pub fn render_help_popup(frame: &mut Frame, area: &Rect, state: &AppState) {
     );
 }
 
-fn render_statistics_popup(frame: &mut Frame, area: &Rect, stats: &StatsState) {
+fn render_statistics_popup(
+    frame: &mut Frame,
+    area: &Rect,
+    stats: &StatsState,
+    settings: &Settings,
+) {
+    let colors = &settings.colors;
     frame.render_widget(Clear, *area);
     frame.render_widget(
         Block::default()
-            .title("Statistics")
+            .title(format!("Statistics by {}", stats.period))
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(STAT_CLR).bold()),
+            .border_style(Style::default().fg(colors.stats).bold()),
         *area,
     );
 
+    let (start_name, start_format) = match stats.period {
+        Period::Week => ("Week Start", settings.date_format.as_str()),
+        Period::Month => ("Month", "%m/%Y"),
+        Period::Year => ("Year", "%Y"),
+    };
     let header = format!(
-        "  {:>10}{:>7}{:>6}{:>6}{:>6}{:>6}{:>8}",
-        "Week Start", "Hours", "Work", "Sick", "HO", "UnOf", "AmtDif",
+        "  {:>10}{:>8}{:>8}{:>6}{:>6}{:>6}{:>6}{:>6}{:>6}{:>8}{:>9}",
+        start_name,
+        "Target",
+        "Hours",
+        "Work",
+        "Sick",
+        "Vac",
+        "Comp",
+        "HO",
+        "UnOf",
+        "AmtDif",
+        "Balance",
     )
     .bold()
-    .fg(ORANGE);
+    .fg(colors.accent);
 
     let inner = Rect {
         x: area.x + 1,
         y: area.y + 1,
-        width: 54,
+        width: 82,
         height: area.height - 2,
     };
 
     let mut tot_surplus = Duration::minutes(0);
 
     let stat_collect = stats
-        .weekly
+        .periods
         .iter()
-        .map(|(week_start, stat)| {
-            let surplus = stat.work
-                - Duration::milliseconds((stats.week_hours * 60.0 * 60.0 * 1000.0) as i64);
+        .map(|(start, stat)| {
+            let surplus = stat.surplus();
             tot_surplus = surplus + tot_surplus;
-            let s_mins = surplus.num_minutes();
             ListItem::new(format!(
-                "{:>10}{:>7}{:>6}{:>6}{:>6}{:>6}  {}{:>2}:{:>02}",
-                week_start.format("%d.%m.%y"),
+                "{:>10}{:>8}{:>8}{:>6}{:>6}{:>6}{:>6}{:>6}{:>6}{:>8}{:>9}",
+                start.format(start_format),
+                hm_from_duration(stat.target),
                 hm_from_duration(stat.work),
                 stat.active_days,
                 stat.sick_days,
+                stat.vacation_days,
+                stat.compensation_days,
                 stat.home_office_days,
                 stat.active_days - stat.home_office_days,
-                if s_mins >= 0 { "+" } else { "-" },
-                (s_mins / 60).abs(),
-                (s_mins % 60).abs(),
+                signed_hm_from_duration(surplus),
+                signed_hm_from_duration(stat.balance),
             ))
         })
         .collect::<Vec<_>>();
@@ -437,58 +498,78 @@ fn render_statistics_popup(frame: &mut Frame, area: &Rect, stats: &StatsState) {
         height: inner.height,
     };
 
-    let total = Text::from(vec![
+    // a collection of only holidays, vacation or sick days has no work days to average over
+    let per_work_day = |time: chrono::Duration| match stats.total.active_days {
+        0 => String::from("-"),
+        active_days => hm_from_duration(chrono::Duration::minutes(
+            time.num_minutes() / active_days as i64,
+        )),
+    };
+    let mut total = Text::from(vec![
         Line::from(vec![
-            "Work Time: ".fg(STAT_CLR),
+            "Work Time: ".fg(colors.stats),
             hm_from_duration(stats.total.work).into(),
         ]),
         Line::from(vec![
-            "Avg:".fg(STAT_CLR),
-            hm_from_duration(chrono::Duration::minutes(
-                stats.total.work.num_minutes() / stats.total.active_days as i64,
-            ))
-            .into(),
+            "Target:    ".fg(colors.stats),
+            hm_from_duration(stats.total.target).into(),
+        ]),
+        Line::from(vec![
+            "Avg:".fg(colors.stats),
+            per_work_day(stats.total.work).into(),
             " / work day".into(),
         ]),
         "".into(),
         Line::from(vec![
-            "Break Time:".fg(STAT_CLR),
+            "Break Time:".fg(colors.stats),
             hm_from_duration(stats.total.brk).into(),
         ]),
         Line::from(vec![
-            "Avg:".fg(STAT_CLR),
-            format!(
-                "{} / work day",
-                hm_from_duration(chrono::Duration::minutes(
-                    stats.total.brk.num_minutes() / stats.total.active_days as i64
-                ))
-            )
-            .into(),
+            "Avg:".fg(colors.stats),
+            format!("{} / work day", per_work_day(stats.total.brk)).into(),
         ]),
         Line::from(""),
         Line::from(vec![
-            "Days: ".fg(STAT_CLR).bold(),
+            "Days: ".fg(colors.stats).bold(),
             format!("{:4}", stats.total.num_days).into(),
         ]),
-        Line::from("Worked".fg(STAT_CLR)),
+        Line::from("Worked".fg(colors.stats)),
         Line::from(format!(
             "{:4} ({:.1}%)",
             stats.total.active_days,
             stats.total.active_days as f64 / stats.total.num_days as f64 * 100.0
         )),
-        Line::from("Sick".fg(STAT_CLR)),
+        Line::from("Sick".fg(colors.stats)),
         Line::from(format!(
             "{:4} ({:.1}%)",
             stats.total.sick_days,
             stats.total.sick_days as f64 / stats.total.num_days as f64 * 100.0
         )),
-        Line::from("Home Office".fg(STAT_CLR)),
+        Line::from("Vacation".fg(colors.stats)),
+        Line::from(format!(
+            "{:4} ({:.1}%)",
+            stats.total.vacation_days,
+            stats.total.vacation_days as f64 / stats.total.num_days as f64 * 100.0
+        )),
+        Line::from("Compensation".fg(colors.stats)),
+        Line::from(format!(
+            "{:4} ({:.1}%)",
+            stats.total.compensation_days,
+            stats.total.compensation_days as f64 / stats.total.num_days as f64 * 100.0
+        )),
+        Line::from("Holidays".fg(colors.stats)),
+        Line::from(format!(
+            "{:4} ({:.1}%)",
+            stats.total.holidays,
+            stats.total.holidays as f64 / stats.total.num_days as f64 * 100.0
+        )),
+        Line::from("Home Office".fg(colors.stats)),
         Line::from(format!(
             "{:4} ({:.1}%)",
             stats.total.home_office_days,
             stats.total.home_office_days as f64 / stats.total.num_days as f64 * 100.0
         )),
-        Line::from("Total Surplus".fg(STAT_CLR)),
+        Line::from("Total Surplus".fg(colors.stats)),
         Line::from(format!(
             "{}{:3}:{:2}",
             if tot_surplus.num_minutes() >= 0 {
@@ -499,14 +580,77 @@ fn render_statistics_popup(frame: &mut Frame, area: &Rect, stats: &StatsState) {
             tot_surplus.num_minutes().abs() / 60,
             tot_surplus.num_minutes().abs() % 60,
         )),
+        Line::from("Flex Balance".fg(colors.stats)),
+        Line::from(signed_hm_from_duration(stats.total.balance)),
     ]);
+    if let Some(vacation) = &stats.vacation {
+        total.extend([
+            Line::from(""),
+            Line::from(
+                format!("Vacation {}", vacation.year)
+                    .fg(colors.stats)
+                    .bold(),
+            ),
+            Line::from(vec![
+                "Entitled: ".fg(colors.stats),
+                format!(
+                    "{}",
+                    vacation.entitlement + vacation.carried - vacation.expired
+                )
+                .into(),
+            ]),
+            Line::from(vec![
+                "Taken:    ".fg(colors.stats),
+                format!("{}", vacation.taken).into(),
+            ]),
+            Line::from(vec![
+                "Planned:  ".fg(colors.stats),
+                format!("{}", vacation.planned).into(),
+            ]),
+            Line::from(vec![
+                "Remaining:".fg(colors.stats),
+                format!("{}", vacation.remaining()).into(),
+            ]),
+        ]);
+    }
+    if !stats.bookings.is_empty() {
+        total.extend([
+            Line::from(""),
+            Line::from("Bookings".fg(colors.stats).bold()),
+        ]);
+        total.extend(stats.bookings.iter().flat_map(|booking| {
+            [
+                Line::from(
+                    format!(
+                        "{} {}",
+                        booking.date.format(&settings.date_format),
+                        booking.kind
+                    )
+                    .fg(colors.stats),
+                ),
+                Line::from(signed_hm_from_duration(booking.amount)),
+            ]
+        }));
+    }
+    if !stats.total.projects.is_empty() {
+        total.extend([
+            Line::from(""),
+            Line::from("Projects".fg(colors.stats).bold()),
+        ]);
+        total.extend(stats.total.projects.iter().map(|(project, time)| {
+            Line::from(vec![
+                format!("{project}: ").fg(colors.stats),
+                hm_from_duration(*time).into(),
+            ])
+        }));
+    }
     frame.render_widget(
         Paragraph::new(total).style(Style::default()).block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(STAT_CLR))
+                .border_style(Style::default().fg(colors.stats))
                 .title("Total")
-                .title_style(Style::default().bold().fg(STAT_CLR)),
+                .title_style(Style::default().bold().fg(colors.stats)),
         ),
         right,
     );
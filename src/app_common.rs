@@ -1,8 +1,19 @@
+use std::collections::HashMap;
+
+use crate::balance::Booking;
+use crate::check::check;
+use crate::collection::Collection;
+pub use crate::config::Settings;
 use crate::editor::{EditBufs, EditField, EditMode};
+use crate::history::History;
+use crate::schedule::Schedule;
+use crate::stat::{Period, StatUnit};
+use crate::storage::Storage;
+use crate::vacation::VacationYear;
 use crate::work_day::WorkDay;
 use chrono::NaiveDate;
-use crate::stat::StatUnit;
 
+/// Default colors, see [`crate::config::Colors`]
 pub mod colors {
     use ratatui::prelude::Color;
     pub const ORANGE: Color = Color::Rgb(255, 140, 0);
@@ -29,24 +40,42 @@ pub enum AppMode {
 pub enum Message {
     Info(String),
     Error(String),
+    /// The collection changed on disk before a write, the list offers to reload, merge or
+    /// overwrite it
+    Conflict(String),
     None,
 }
 
 pub struct StatsState {
-    pub weekly: Vec<(NaiveDate, StatUnit)>,
+    /// Length of the periods in `periods`
+    pub period: Period,
+    /// Statistics of each period with entries, by the start of the period
+    pub periods: Vec<(NaiveDate, StatUnit)>,
     pub total: StatUnit,
+    /// Bookings on the flex time account, sorted by date
+    pub bookings: Vec<Booking>,
+    /// Vacation account of the current year, if an entitlement is configured
+    pub vacation: Option<VacationYear>,
     pub scroll: usize,
-    pub week_hours: f32,
-}
-
-pub struct Settings {
-    pub week_hours: f32,
 }
 
 pub struct AppState {
     pub file_path: String,
+    /// Where `days` and `bookings` are saved, see [`crate::storage::open`]
+    pub storage: Box<dyn Storage>,
     pub days: Vec<WorkDay>,
+    /// Bookings on the flex time account, kept as they are in the file
+    pub bookings: Vec<Booking>,
+    /// Days and bookings as they were last loaded or saved, the base of a merge
+    pub saved: Collection,
+    /// Changes to `days` that can be undone
+    pub history: History,
     pub settings: Settings,
+    /// Targets of `settings`, which do not change while the app is open
+    pub schedule: Schedule,
+    /// Violations of the working time rules by the index of the day in `days`, see
+    /// [`AppState::days_changed`]
+    pub violations: HashMap<usize, Vec<String>>,
     pub selected: Option<usize>,
     pub mode: AppMode,
     pub message: Message,
@@ -55,28 +84,150 @@ pub struct AppState {
 }
 
 impl AppState {
-    pub fn write(&mut self) -> Result<(), ()> {
-        use std::fs::File;
-        use std::io::BufWriter;
-
-        let writer = BufWriter::new(File::create(&self.file_path).map_err(|err| {
-            self.message = Message::Error(format!("Could not open file {}: {err}", self.file_path))
-        })?);
-        let w_res = serde_json::to_writer_pretty(writer, &self.days);
-        match w_res {
-            Ok(()) => (),
+    /// Saves the collection after a backup of the previous state and reports the outcome in the
+    /// message area, returns whether the collection was saved. Nothing is saved if the collection
+    /// was changed by someone else in the meantime.
+    pub fn write(&mut self) -> bool {
+        match self.storage.changed() {
+            Ok(false) => self.overwrite(),
+            Ok(true) => {
+                self.message = Message::Conflict(format!(
+                    "{} changed on disk: R reloads it, M merges your changes into it, W \
+                     overwrites it",
+                    &self.file_path
+                ));
+                false
+            }
             Err(err) => {
-                self.message =
-                    Message::Error(format!("Could not open file {}: {err}", &self.file_path))
+                self.message = Message::Error(err);
+                false
             }
         }
-        self.message = Message::Info(format!(
-            "Wrote {} entries to file {}",
-            self.days.len(),
-            &self.file_path
-        ));
+    }
+
+    /// Saves the collection like [`AppState::write`], even if it changed on disk
+    pub fn overwrite(&mut self) -> bool {
+        let saved = crate::backup::rotate(&self.file_path, self.settings.backups)
+            .and_then(|()| self.storage.save(&self.days, &self.bookings));
+        match saved {
+            Ok(()) => {
+                self.saved = Collection {
+                    days: self.days.clone(),
+                    bookings: self.bookings.clone(),
+                };
+                self.message = Message::Info(format!(
+                    "Wrote {} entries to file {}",
+                    self.days.len(),
+                    &self.file_path
+                ));
+                true
+            }
+            Err(err) => {
+                self.message = Message::Error(err);
+                false
+            }
+        }
+    }
+
+    /// Replaces the days and bookings with the ones on disk, discarding all unsaved changes
+    pub fn reload(&mut self) {
+        match self.storage.load() {
+            Ok(collection) => {
+                self.set_collection(collection.clone());
+                self.saved = collection;
+                self.history.clear();
+                self.message = Message::Info(format!(
+                    "Reloaded {} entries from {}",
+                    self.days.len(),
+                    &self.file_path
+                ));
+            }
+            Err(err) => self.message = Message::Error(err),
+        }
+    }
+
+    /// Applies the unsaved changes to the days and bookings on disk, without saving the result
+    pub fn merge(&mut self) {
+        let theirs = match self.storage.load() {
+            Ok(collection) => collection,
+            Err(err) => {
+                self.message = Message::Error(err);
+                return;
+            }
+        };
+        let ours = Collection {
+            days: std::mem::take(&mut self.days),
+            bookings: std::mem::take(&mut self.bookings),
+        };
+        let (merged, conflicts) = ours.merge(&self.saved, theirs.clone());
+        self.set_collection(merged);
+        self.saved = theirs;
+        self.message = Message::Info(if conflicts == 0 {
+            format!("Merged your changes into {}, w saves them", &self.file_path)
+        } else {
+            format!(
+                "Merged your changes into {}, {conflicts} entries were changed differently on \
+                 both sides, check them before saving with w",
+                &self.file_path
+            )
+        });
+    }
+
+    /// Reverts the last change to the days
+    pub fn undo(&mut self) {
+        let undone = self.history.undo(&mut self.days);
+        self.days_changed();
+        self.show_history(undone, "Already at oldest change");
+    }
+
+    /// Applies the last undone change to the days again
+    pub fn redo(&mut self) {
+        let redone = self.history.redo(&mut self.days);
+        self.days_changed();
+        self.show_history(redone, "Already at newest change");
+    }
+
+    /// Selects the entry affected by an undo or redo and reports it in the message area
+    fn show_history(&mut self, result: Option<Result<(String, usize), String>>, none: &str) {
+        match result {
+            Some(Ok((description, index))) => {
+                self.select(index);
+                self.message = Message::Info(description);
+            }
+            Some(Err(err)) => self.message = Message::Error(err),
+            None => self.message = Message::Info(String::from(none)),
+        }
+    }
+
+    /// Shows `collection` in the list, sorted by date
+    fn set_collection(&mut self, collection: Collection) {
+        self.days = collection.days;
+        self.days.sort_by_key(|day| day.date);
+        self.bookings = collection.bookings;
+        self.days_changed();
+        self.select(self.selected.unwrap_or(usize::MAX));
+        self.mode = AppMode::ListOnly;
+    }
+
+    /// Checks the days against the working time rules again, has to be called after every change
+    /// to `days`
+    pub fn days_changed(&mut self) {
+        self.violations.clear();
+        for (index, violation) in check(&self.days, &self.settings.rules) {
+            self.violations
+                .entry(index)
+                .or_default()
+                .push(violation.to_string());
+        }
+    }
 
-        Ok(())
+    /// Selects the entry at `index`, the last one if it is out of range and none if there are no
+    /// entries
+    pub fn select(&mut self, index: usize) {
+        self.selected = match self.days.len() {
+            0 => None,
+            len => Some(index.min(len - 1)),
+        };
     }
 
     pub fn next_day(&mut self) -> Option<usize> {
@@ -0,0 +1,104 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, NaiveDateTime};
+
+use crate::storage::write_atomic;
+
+/// Copy of a collection file from before a save
+pub struct Backup {
+    pub path: PathBuf,
+    pub created: NaiveDateTime,
+}
+
+/// Names of the backup files, followed by the extension of the collection
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H-%M-%S%.3f";
+
+/// Directory of the backups of the collection at `file_path`, next to it with a `.backups`
+/// suffix, e.g. `work_times.json.backups`
+pub fn backup_dir(file_path: &str) -> PathBuf {
+    let mut dir = PathBuf::from(file_path).into_os_string();
+    dir.push(".backups");
+    PathBuf::from(dir)
+}
+
+/// Backups of the collection at `file_path`, the newest first
+pub fn list(file_path: &str) -> Result<Vec<Backup>, String> {
+    let dir = backup_dir(file_path);
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(format!(
+                "Could not read backups in {}: {err}",
+                dir.display()
+            ))
+        }
+    };
+    let mut backups = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let path = entry.path();
+            let name = path.file_stem()?.to_str()?;
+            let created = NaiveDateTime::parse_from_str(name, TIMESTAMP_FORMAT).ok()?;
+            Some(Backup { path, created })
+        })
+        .collect::<Vec<_>>();
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.created));
+    Ok(backups)
+}
+
+/// Copies the collection at `file_path` into its backup directory, returns `None` if the file does
+/// not exist yet
+pub fn create(file_path: &str) -> Result<Option<PathBuf>, String> {
+    let path = Path::new(file_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let dir = backup_dir(file_path);
+    std::fs::create_dir_all(&dir)
+        .map_err(|err| format!("Could not create backup directory {}: {err}", dir.display()))?;
+    let mut name = Local::now()
+        .naive_local()
+        .format(TIMESTAMP_FORMAT)
+        .to_string();
+    if let Some(extension) = path.extension() {
+        name.push('.');
+        name.push_str(&extension.to_string_lossy());
+    }
+    let backup = dir.join(name);
+    std::fs::copy(path, &backup).map_err(|err| format!("Could not back up {file_path}: {err}"))?;
+    Ok(Some(backup))
+}
+
+/// Deletes all but the newest `keep` backups of the collection at `file_path`
+pub fn prune(file_path: &str, keep: usize) -> Result<(), String> {
+    for backup in list(file_path)?.into_iter().skip(keep) {
+        std::fs::remove_file(&backup.path).map_err(|err| {
+            format!(
+                "Could not delete old backup {}: {err}",
+                backup.path.display()
+            )
+        })?;
+    }
+    Ok(())
+}
+
+/// Backs up the collection at `file_path` before it is overwritten, keeping the newest `keep`
+/// backups. Nothing is backed up if `keep` is 0.
+pub fn rotate(file_path: &str, keep: usize) -> Result<(), String> {
+    if keep == 0 {
+        return Ok(());
+    }
+    create(file_path)?;
+    prune(file_path, keep)
+}
+
+/// Replaces the collection at `file_path` with `backup`. The current state is backed up first,
+/// so the restore can be undone.
+pub fn restore(file_path: &str, backup: &Backup) -> Result<(), String> {
+    let data = std::fs::read(&backup.path)
+        .map_err(|err| format!("Could not read backup {}: {err}", backup.path.display()))?;
+    create(file_path)?;
+    write_atomic(Path::new(file_path), &data)
+        .map_err(|err| format!("Could not write file {file_path}: {err}"))
+}
@@ -0,0 +1,268 @@
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::stat::{Period, StatUnit};
+use crate::work_day::parse_duration;
+
+/// Kinds of manual bookings on the flex time account
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BookingKind {
+    /// Balance when the collection starts
+    Opening,
+    /// Signed change of the balance
+    Correction,
+    /// Overtime that was paid out and leaves the account
+    Payout,
+    /// Balance set by HR, e.g. at the end of the year
+    Reset,
+}
+
+impl BookingKind {
+    const NAMES: [(&'static str, BookingKind); 4] = [
+        ("opening", BookingKind::Opening),
+        ("correction", BookingKind::Correction),
+        ("payout", BookingKind::Payout),
+        ("reset", BookingKind::Reset),
+    ];
+}
+
+impl std::str::FromStr for BookingKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BookingKind::NAMES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+            .map(|(_, kind)| *kind)
+            .ok_or(format!(
+                "unknown booking {s}, expected one of {}",
+                BookingKind::NAMES.map(|(name, _)| name).join(", ")
+            ))
+    }
+}
+
+impl std::fmt::Display for BookingKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (name, _) = BookingKind::NAMES
+            .iter()
+            .find(|(_, kind)| kind == self)
+            .unwrap();
+        write!(f, "{name}")
+    }
+}
+
+/// Manual booking on the flex time account
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Booking {
+    pub date: NaiveDate,
+    pub kind: BookingKind,
+    #[serde(with = "signed_hm_format")]
+    pub amount: Duration,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub note: String,
+}
+
+impl Booking {
+    /// Balance after this booking, given the balance before it
+    pub fn apply(&self, balance: Duration) -> Duration {
+        match self.kind {
+            BookingKind::Opening | BookingKind::Reset => self.amount,
+            BookingKind::Correction => balance + self.amount,
+            BookingKind::Payout => balance - self.amount.abs(),
+        }
+    }
+}
+
+/// Checks that `booking` can be added to `bookings`: there is only one opening and no booking
+/// comes before it, as the opening sets the balance and would discard them
+pub fn check_booking(bookings: &[Booking], booking: &Booking) -> Result<(), String> {
+    let opening = bookings
+        .iter()
+        .find(|other| other.kind == BookingKind::Opening);
+    match (booking.kind, opening) {
+        (BookingKind::Opening, Some(opening)) => Err(format!(
+            "There already is an opening balance on {}",
+            opening.date
+        )),
+        (BookingKind::Opening, None) => match bookings
+            .iter()
+            .filter(|other| other.date < booking.date)
+            .min_by_key(|other| other.date)
+        {
+            Some(earlier) => Err(format!(
+                "The opening balance has to be the first booking, there is a {} on {}",
+                earlier.kind, earlier.date
+            )),
+            None => Ok(()),
+        },
+        (kind, Some(opening)) if booking.date < opening.date => Err(format!(
+            "The {kind} on {} is before the opening balance on {}",
+            booking.date, opening.date
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Parses a duration as `HH:MM` with an optional leading sign
+pub fn parse_signed_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    match text.strip_prefix('-') {
+        Some(rest) => parse_duration(rest).map(|duration| -duration),
+        None => parse_duration(text.strip_prefix('+').unwrap_or(text)),
+    }
+}
+
+/// (De)serializes a duration as `+HH:MM` or `-HH:MM`
+mod signed_hm_format {
+    use chrono::Duration;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::disp_utils::signed_hm_from_duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&signed_hm_from_duration(*duration))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        super::parse_signed_duration(&String::deserialize(deserializer)?)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Sets the flex balance at the end of each of the sorted `periods` and returns the balance after
+/// all bookings. A booking takes effect at the start of its period, before the surplus of the
+/// period is added, bookings in periods without entries count for the next period with entries.
+pub fn running_balance(
+    periods: &mut [(NaiveDate, StatUnit)],
+    period: Period,
+    bookings: &[Booking],
+) -> Duration {
+    let mut bookings = bookings.iter().collect::<Vec<_>>();
+    bookings.sort_by_key(|booking| booking.date);
+    let mut pending = bookings.into_iter().peekable();
+
+    let mut balance = Duration::zero();
+    for (start, stat) in periods.iter_mut() {
+        let end = period.end(*start);
+        while let Some(booking) = pending.next_if(|booking| booking.date <= end) {
+            balance = booking.apply(balance);
+        }
+        balance = balance + stat.surplus();
+        stat.balance = balance;
+    }
+    pending.fold(balance, |balance, booking| booking.apply(balance))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, month, day).unwrap()
+    }
+
+    fn booking(date: NaiveDate, kind: BookingKind, hours: i64) -> Booking {
+        Booking {
+            date,
+            kind,
+            amount: Duration::hours(hours),
+            note: String::new(),
+        }
+    }
+
+    /// Weeks starting at the mondays `starts` with a surplus of `hours` each
+    fn weeks(starts: &[(u32, u32)], hours: i64) -> Vec<(NaiveDate, StatUnit)> {
+        starts
+            .iter()
+            .map(|(month, day)| {
+                let stat = StatUnit {
+                    work: Duration::hours(40 + hours),
+                    target: Duration::hours(40),
+                    ..StatUnit::default()
+                };
+                (date(*month, *day), stat)
+            })
+            .collect()
+    }
+
+    fn balances(weeks: &[(NaiveDate, StatUnit)]) -> Vec<i64> {
+        weeks
+            .iter()
+            .map(|(_, stat)| stat.balance.num_hours())
+            .collect()
+    }
+
+    #[test]
+    fn surplus_adds_up() {
+        let mut weeks = weeks(&[(1, 6), (1, 13), (1, 20)], 2);
+        assert_eq!(
+            running_balance(&mut weeks, Period::Week, &[]),
+            Duration::hours(6)
+        );
+        assert_eq!(balances(&weeks), vec![2, 4, 6]);
+    }
+
+    #[test]
+    fn bookings_at_the_start_of_their_week() {
+        let mut weeks = weeks(&[(1, 6), (1, 13), (1, 20)], 1);
+        // unsorted, and the correction falls on the sunday of the first week
+        let bookings = [
+            booking(date(1, 15), BookingKind::Payout, -3),
+            booking(date(1, 12), BookingKind::Correction, 2),
+            booking(date(1, 6), BookingKind::Opening, 10),
+        ];
+        assert_eq!(
+            running_balance(&mut weeks, Period::Week, &bookings),
+            Duration::hours(12)
+        );
+        assert_eq!(balances(&weeks), vec![13, 11, 12]);
+    }
+
+    #[test]
+    fn reset_discards_earlier_balance() {
+        let mut weeks = weeks(&[(1, 6), (1, 13)], 5);
+        let bookings = [booking(date(1, 13), BookingKind::Reset, 0)];
+        running_balance(&mut weeks, Period::Week, &bookings);
+        assert_eq!(balances(&weeks), vec![5, 5]);
+    }
+
+    #[test]
+    fn bookings_without_entries() {
+        // the week of the 13th has no entries, its booking counts for the next week
+        let mut weeks = weeks(&[(1, 6), (1, 20)], 1);
+        let bookings = [
+            booking(date(1, 14), BookingKind::Correction, 4),
+            booking(date(2, 3), BookingKind::Payout, 2),
+        ];
+        assert_eq!(
+            running_balance(&mut weeks, Period::Week, &bookings),
+            Duration::hours(4)
+        );
+        assert_eq!(balances(&weeks), vec![1, 6]);
+    }
+
+    #[test]
+    fn monthly_balance() {
+        let mut months = weeks(&[(1, 1), (2, 1)], 3);
+        let bookings = [booking(date(2, 20), BookingKind::Correction, -1)];
+        running_balance(&mut months, Period::Month, &bookings);
+        assert_eq!(balances(&months), vec![3, 5]);
+    }
+
+    #[test]
+    fn opening_comes_first() {
+        let opening = booking(date(1, 6), BookingKind::Opening, 10);
+        let correction = booking(date(1, 13), BookingKind::Correction, 1);
+        assert!(check_booking(&[], &opening).is_ok());
+        assert!(check_booking(std::slice::from_ref(&correction), &opening).is_ok());
+        assert!(check_booking(std::slice::from_ref(&opening), &correction).is_ok());
+
+        assert!(check_booking(std::slice::from_ref(&opening), &opening).is_err());
+        let later_opening = booking(date(1, 20), BookingKind::Opening, 10);
+        assert!(check_booking(std::slice::from_ref(&correction), &later_opening).is_err());
+        let early_payout = booking(date(1, 1), BookingKind::Payout, 1);
+        assert!(check_booking(&[opening], &early_payout).is_err());
+    }
+}
@@ -0,0 +1,366 @@
+use chrono::{Duration, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::disp_utils::hm_from_duration;
+use crate::schedule::hours;
+use crate::work_day::WorkDay;
+
+/// Thresholds of the working time checks, by default those of the german working time law
+/// (Arbeitszeitgesetz)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rules {
+    /// Maximum work time of a day
+    pub max_work_hours: f32,
+    /// Work time after which `short_break_minutes` of breaks are required
+    pub short_break_after_hours: f32,
+    pub short_break_minutes: u32,
+    /// Work time after which `long_break_minutes` of breaks are required
+    pub long_break_after_hours: f32,
+    pub long_break_minutes: u32,
+    /// Minimum rest between the end of one day and the start of the next
+    pub min_rest_hours: f32,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            max_work_hours: 10.0,
+            short_break_after_hours: 6.0,
+            short_break_minutes: 30,
+            long_break_after_hours: 9.0,
+            long_break_minutes: 45,
+            min_rest_hours: 11.0,
+        }
+    }
+}
+
+impl Rules {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, hours) in [
+            ("max_work_hours", self.max_work_hours),
+            ("short_break_after_hours", self.short_break_after_hours),
+            ("long_break_after_hours", self.long_break_after_hours),
+            ("min_rest_hours", self.min_rest_hours),
+        ] {
+            if !(0.0..=24.0).contains(&hours) {
+                return Err(format!("invalid {name} {hours}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Breaks required after `worked` time
+    fn required_break(&self, worked: Duration) -> Duration {
+        if worked > hours(self.long_break_after_hours) {
+            Duration::minutes(self.long_break_minutes as i64)
+        } else if worked > hours(self.short_break_after_hours) {
+            Duration::minutes(self.short_break_minutes as i64)
+        } else {
+            Duration::zero()
+        }
+    }
+}
+
+/// A rule that is broken on a day
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    TooMuchWork { worked: Duration, max: Duration },
+    MissingBreak { taken: Duration, required: Duration },
+    ShortRest { rest: Duration, min: Duration },
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::TooMuchWork { worked, max } => write!(
+                f,
+                "worked {} of at most {}",
+                hm_from_duration(*worked),
+                hm_from_duration(*max)
+            ),
+            Violation::MissingBreak { taken, required } => write!(
+                f,
+                "break of {} instead of {}",
+                hm_from_duration(*taken),
+                hm_from_duration(*required)
+            ),
+            Violation::ShortRest { rest, min } => write!(
+                f,
+                "rest of {} instead of {} since the previous day",
+                hm_from_duration(*rest),
+                hm_from_duration(*min)
+            ),
+        }
+    }
+}
+
+/// Breaks of `day`, including the gaps between its work sessions
+fn break_time(day: &WorkDay) -> Duration {
+    let gaps = day
+        .sessions()
+        .windows(2)
+        .filter_map(|pair| Some((pair[1].offsets().0 - pair[0].offsets().1?).max(Duration::zero())))
+        .fold(Duration::zero(), |acc, gap| acc + gap);
+    day.break_time() + gaps
+}
+
+/// Start of the first and end of the last work session of a finished `day`
+fn work_span(day: &WorkDay) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    let start = day.date.and_time(day.start()?);
+    let end = day.date.and_time(day.end()?) + day.sessions().last()?.day_offset();
+    Some((start, end))
+}
+
+/// Checks all finished days against `rules`, returns the indices of the violating days in
+/// `days` with their violations, ordered by date. The rest is checked against the previous day
+/// with work. Breaks are only checked on day types that have them, see
+/// [`crate::work_day::DayType::has_breaks`].
+pub fn check(days: &[WorkDay], rules: &Rules) -> Vec<(usize, Violation)> {
+    let mut by_date = days
+        .iter()
+        .enumerate()
+        .filter(|(_, day)| !day.is_running() && !day.sessions().is_empty())
+        .collect::<Vec<_>>();
+    by_date.sort_by_key(|(_, day)| work_span(day));
+
+    let mut violations = Vec::new();
+    let mut previous_end = None;
+    for (index, day) in by_date {
+        let worked = day.worked_time();
+        if worked > hours(rules.max_work_hours) {
+            violations.push((
+                index,
+                Violation::TooMuchWork {
+                    worked,
+                    max: hours(rules.max_work_hours),
+                },
+            ));
+        }
+        let taken = break_time(day);
+        let required = rules.required_break(worked);
+        if day.day_type.has_breaks() && taken < required {
+            violations.push((index, Violation::MissingBreak { taken, required }));
+        }
+        if let Some((start, end)) = work_span(day) {
+            if let Some(previous_end) = previous_end {
+                let rest = start - previous_end;
+                if rest < hours(rules.min_rest_hours) {
+                    violations.push((
+                        index,
+                        Violation::ShortRest {
+                            rest,
+                            min: hours(rules.min_rest_hours),
+                        },
+                    ));
+                }
+            }
+            previous_end = Some(end);
+        }
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use super::*;
+    use crate::work_day::{DayType, Interval};
+
+    fn interval(start: (u32, u32), end: (u32, u32)) -> Interval {
+        Interval {
+            start: NaiveTime::from_hms_opt(start.0, start.1, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(end.0, end.1, 0),
+            next_day: end < start,
+            start_next_day: false,
+        }
+    }
+
+    fn present(day: u32, sessions: &[Interval], breaks: &[Interval]) -> WorkDay {
+        WorkDay::new(
+            NaiveDate::from_ymd_opt(2025, 3, day).unwrap(),
+            DayType::Present {
+                sessions: sessions.to_vec(),
+                breaks: breaks.to_vec(),
+            },
+        )
+    }
+
+    fn violations(days: &[WorkDay]) -> Vec<(usize, Violation)> {
+        check(days, &Rules::default())
+    }
+
+    #[test]
+    fn too_much_work() {
+        let days = [
+            present(
+                3,
+                &[interval((7, 0), (18, 0))],
+                &[interval((12, 0), (12, 45))],
+            ),
+            present(
+                4,
+                &[interval((7, 0), (17, 45))],
+                &[interval((12, 0), (12, 45))],
+            ),
+        ];
+        assert_eq!(
+            violations(&days),
+            vec![(
+                0,
+                Violation::TooMuchWork {
+                    worked: Duration::minutes(615),
+                    max: Duration::hours(10),
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn short_break() {
+        let missing = |taken, required| Violation::MissingBreak {
+            taken: Duration::minutes(taken),
+            required: Duration::minutes(required),
+        };
+        let days = [
+            // exactly 6 hours need no break
+            present(3, &[interval((8, 0), (14, 0))], &[]),
+            present(
+                4,
+                &[interval((8, 0), (14, 30))],
+                &[interval((12, 0), (12, 15))],
+            ),
+            present(
+                5,
+                &[interval((8, 0), (14, 30))],
+                &[interval((12, 0), (12, 30))],
+            ),
+        ];
+        assert_eq!(violations(&days), vec![(1, missing(15, 30))]);
+    }
+
+    #[test]
+    fn long_break() {
+        let missing = Violation::MissingBreak {
+            taken: Duration::minutes(30),
+            required: Duration::minutes(45),
+        };
+        let days = [
+            present(
+                3,
+                &[interval((7, 0), (17, 0))],
+                &[interval((12, 0), (12, 30))],
+            ),
+            present(
+                4,
+                &[interval((7, 0), (17, 15))],
+                &[interval((12, 0), (12, 45))],
+            ),
+        ];
+        assert_eq!(violations(&days), vec![(0, missing)]);
+    }
+
+    #[test]
+    fn gaps_between_sessions_are_breaks() {
+        let days = [
+            present(
+                3,
+                &[interval((7, 0), (12, 0)), interval((12, 45), (17, 0))],
+                &[],
+            ),
+            present(
+                4,
+                &[interval((7, 0), (12, 0)), interval((12, 15), (16, 30))],
+                &[interval((15, 0), (15, 15))],
+            ),
+            present(
+                5,
+                &[interval((8, 0), (12, 0)), interval((12, 10), (14, 40))],
+                &[],
+            ),
+        ];
+        assert_eq!(
+            violations(&days),
+            vec![(
+                2,
+                Violation::MissingBreak {
+                    taken: Duration::minutes(10),
+                    required: Duration::minutes(30),
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn travel_days_have_no_breaks() {
+        let travel = WorkDay::new(
+            NaiveDate::from_ymd_opt(2025, 3, 3).unwrap(),
+            DayType::Travel {
+                sessions: vec![interval((6, 0), (15, 0))],
+            },
+        );
+        assert_eq!(violations(&[travel]), Vec::new());
+    }
+
+    #[test]
+    fn rest_after_overnight_shift() {
+        let days = [
+            present(
+                3,
+                &[interval((22, 0), (6, 0))],
+                &[interval((1, 0), (1, 30))],
+            ),
+            present(4, &[interval((14, 0), (18, 0))], &[]),
+            present(
+                5,
+                &[interval((22, 0), (6, 0))],
+                &[interval((1, 0), (1, 30))],
+            ),
+            present(6, &[interval((17, 0), (21, 0))], &[]),
+        ];
+        assert_eq!(
+            violations(&days),
+            vec![(
+                1,
+                Violation::ShortRest {
+                    rest: Duration::hours(8),
+                    min: Duration::hours(11),
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn configured_rules() {
+        let rules = Rules {
+            max_work_hours: 8.0,
+            short_break_after_hours: 4.0,
+            ..Rules::default()
+        };
+        let day = present(
+            3,
+            &[interval((8, 0), (16, 45))],
+            &[interval((12, 0), (12, 15))],
+        );
+        assert_eq!(
+            check(&[day], &rules),
+            vec![
+                (
+                    0,
+                    Violation::TooMuchWork {
+                        worked: Duration::minutes(510),
+                        max: Duration::hours(8),
+                    }
+                ),
+                (
+                    0,
+                    Violation::MissingBreak {
+                        taken: Duration::minutes(15),
+                        required: Duration::minutes(30),
+                    }
+                )
+            ]
+        );
+    }
+}
@@ -0,0 +1,296 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::balance::Booking;
+use crate::work_day::WorkDay;
+
+/// Version of the collection format written by this version of wd. Older versions are upgraded
+/// step by step on load, see [`upgrade_document`].
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Contents of a collection file, stored as an object with the format `version`, the `days` and
+/// the `bookings`
+#[derive(Debug, Default, Clone)]
+pub struct Collection {
+    pub days: Vec<WorkDay>,
+    pub bookings: Vec<Booking>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Document {
+    /// Checked by [`format_version`] before
+    #[serde(rename = "version")]
+    _version: u32,
+    days: Vec<WorkDay>,
+    #[serde(default)]
+    bookings: Vec<Booking>,
+}
+
+#[derive(Serialize)]
+struct DocumentRef<'a> {
+    version: u32,
+    days: &'a [WorkDay],
+    #[serde(skip_serializing_if = "<[Booking]>::is_empty")]
+    bookings: &'a [Booking],
+}
+
+/// Only the version of a document, the rest is skipped
+#[derive(Deserialize)]
+struct VersionProbe {
+    #[serde(default)]
+    version: u32,
+}
+
+/// Format version of a collection document, 0 for the unversioned formats
+pub fn format_version(text: &str) -> Result<u32, String> {
+    match text.trim_start().chars().next() {
+        Some('[') => return Ok(0),
+        Some('{') => (),
+        _ => {
+            return Err(String::from(
+                "expected an object with the format version and the days",
+            ))
+        }
+    }
+    serde_json::from_str::<VersionProbe>(text)
+        .map(|probe| probe.version)
+        .map_err(|err| err.to_string())
+}
+
+/// Upgrades a document of format `version` to the next version
+fn upgrade_document(version: u32, document: Value) -> Result<Value, String> {
+    match version {
+        // a plain array of days, or an object with days and bookings
+        0 => match document {
+            Value::Array(days) => Ok(serde_json::json!({ "version": 1, "days": days })),
+            Value::Object(mut document) => {
+                document.insert(String::from("version"), Value::from(1));
+                Ok(Value::Object(document))
+            }
+            _ => Err(String::from(
+                "expected an array of days or an object with days and bookings",
+            )),
+        },
+        _ => Err(format!("unknown format version {version}")),
+    }
+}
+
+impl Collection {
+    /// Applies the changes from `base` to `self` onto `theirs`, a newer version of `base` that was
+    /// changed elsewhere. Returns the merged collection and the number of entries that were changed
+    /// differently on both sides, see [`merge_entries`].
+    pub fn merge(&self, base: &Collection, theirs: Collection) -> (Collection, usize) {
+        let (days, day_conflicts) =
+            merge_entries(&base.days, &self.days, theirs.days, |day| day.date);
+        let (bookings, booking_conflicts) =
+            merge_entries(&base.bookings, &self.bookings, theirs.bookings, |booking| {
+                booking.date
+            });
+        (
+            Collection { days, bookings },
+            day_conflicts + booking_conflicts,
+        )
+    }
+
+    /// Reads a collection of any format version
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let version = format_version(text)?;
+        if version > FORMAT_VERSION {
+            return Err(format!(
+                "format version {version} is newer than the supported version {FORMAT_VERSION}, \
+                 please update wd"
+            ));
+        }
+        // the current version is parsed directly, to keep the position of parse errors
+        let document: Document = if version == FORMAT_VERSION {
+            serde_json::from_str(text).map_err(|err| err.to_string())?
+        } else {
+            let mut document = serde_json::from_str(text).map_err(|err| err.to_string())?;
+            for version in version..FORMAT_VERSION {
+                document = upgrade_document(version, document)?;
+            }
+            serde_json::from_value(document).map_err(|err| err.to_string())?
+        };
+        Ok(Self {
+            days: document.days,
+            bookings: document.bookings,
+        })
+    }
+}
+
+/// Serializes a collection in the current format, see [`Collection`] for the layout
+pub fn to_json(days: &[WorkDay], bookings: &[Booking]) -> Result<String, String> {
+    serde_json::to_string_pretty(&DocumentRef {
+        version: FORMAT_VERSION,
+        days,
+        bookings,
+    })
+    .map_err(|err| err.to_string())
+}
+
+/// Entries of `other` that are not in `base` and entries of `base` that are not in `other`,
+/// equal entries are matched one to one
+fn diff<'a, T: Clone + PartialEq>(base: &'a [T], other: &[T]) -> (Vec<T>, Vec<&'a T>) {
+    let mut added = other.to_vec();
+    let mut removed = Vec::new();
+    for entry in base {
+        if !take(&mut added, entry) {
+            removed.push(entry);
+        }
+    }
+    (added, removed)
+}
+
+/// Removes the first entry equal to `entry` from `entries`, returns whether there was one
+fn take<T: PartialEq>(entries: &mut Vec<T>, entry: &T) -> bool {
+    match entries.iter().position(|other| other == entry) {
+        Some(index) => {
+            entries.remove(index);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Three-way merge of two lists changed from `base`. Entries are compared as a whole, so an edited
+/// entry is a removed and an added one, and changes made on both sides are applied once. Returns
+/// the merged list and the number of entries that were changed differently on both sides: removed
+/// by both, with a replacement on the same `date` on either side. The replacements of both sides
+/// are kept for them.
+fn merge_entries<T: Clone + PartialEq>(
+    base: &[T],
+    ours: &[T],
+    theirs: Vec<T>,
+    date: fn(&T) -> NaiveDate,
+) -> (Vec<T>, usize) {
+    let (mut our_added, our_removed) = diff(base, ours);
+    let (mut their_added, _) = diff(base, &theirs);
+    our_added.retain(|entry| !take(&mut their_added, entry));
+
+    let mut merged = theirs;
+    let mut conflicts = 0;
+    for entry in our_removed {
+        let replaced = |added: &[T]| added.iter().any(|other| date(other) == date(entry));
+        if !take(&mut merged, entry) && (replaced(&our_added) || replaced(&their_added)) {
+            conflicts += 1;
+        }
+    }
+    merged.extend(our_added);
+    (merged, conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::work_day::DayType;
+
+    fn day(day: u32, note: &str) -> WorkDay {
+        WorkDay {
+            note: String::from(note),
+            ..WorkDay::new(
+                NaiveDate::from_ymd_opt(2025, 3, day).unwrap(),
+                DayType::Vacation,
+            )
+        }
+    }
+
+    fn collection(days: &[WorkDay]) -> Collection {
+        Collection {
+            days: days.to_vec(),
+            bookings: Vec::new(),
+        }
+    }
+
+    /// Merges `ours` into `theirs`, both changed from `base`, with the merged days sorted by date
+    /// and note
+    fn merge(base: &[WorkDay], ours: &[WorkDay], theirs: &[WorkDay]) -> (Vec<WorkDay>, usize) {
+        let (mut merged, conflicts) = collection(ours).merge(&collection(base), collection(theirs));
+        merged
+            .days
+            .sort_by(|a, b| (a.date, &a.note).cmp(&(b.date, &b.note)));
+        (merged.days, conflicts)
+    }
+
+    #[test]
+    fn merge_independent_changes() {
+        let base = [day(3, ""), day(4, ""), day(5, "")];
+        let ours = [day(3, "ours"), day(4, ""), day(5, ""), day(6, "")];
+        let theirs = [day(3, ""), day(5, "theirs"), day(7, "")];
+        assert_eq!(
+            merge(&base, &ours, &theirs),
+            (
+                vec![day(3, "ours"), day(5, "theirs"), day(6, ""), day(7, "")],
+                0
+            )
+        );
+    }
+
+    #[test]
+    fn merge_same_changes() {
+        let base = [day(3, ""), day(4, "")];
+        let changed = [day(3, "both"), day(5, "")];
+        assert_eq!(
+            merge(&base, &changed, &changed),
+            (vec![day(3, "both"), day(5, "")], 0)
+        );
+    }
+
+    #[test]
+    fn merge_edit_edit() {
+        let base = [day(3, "")];
+        assert_eq!(
+            merge(&base, &[day(3, "ours")], &[day(3, "theirs")]),
+            (vec![day(3, "ours"), day(3, "theirs")], 1)
+        );
+    }
+
+    #[test]
+    fn merge_delete_delete() {
+        let base = [day(3, ""), day(4, "")];
+        assert_eq!(
+            merge(&base, &[day(4, "")], &[day(4, "")]),
+            (vec![day(4, "")], 0)
+        );
+    }
+
+    #[test]
+    fn merge_delete_edit() {
+        let base = [day(3, ""), day(4, "")];
+        assert_eq!(
+            merge(&base, &[day(4, "")], &[day(3, "theirs"), day(4, "")]),
+            (vec![day(3, "theirs"), day(4, "")], 1)
+        );
+        assert_eq!(
+            merge(&base, &[day(3, "ours"), day(4, "")], &[day(4, "")]),
+            (vec![day(3, "ours"), day(4, "")], 1)
+        );
+    }
+
+    #[test]
+    fn merge_bookings() {
+        let booking = |amount| Booking {
+            date: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            kind: crate::balance::BookingKind::Correction,
+            amount: chrono::Duration::hours(amount),
+            note: String::new(),
+        };
+        let base = Collection {
+            days: Vec::new(),
+            bookings: vec![booking(1)],
+        };
+        let ours = Collection {
+            days: Vec::new(),
+            bookings: vec![booking(1), booking(2)],
+        };
+        let theirs = Collection {
+            days: vec![day(3, "")],
+            bookings: Vec::new(),
+        };
+        let (merged, conflicts) = ours.merge(&base, theirs);
+        assert_eq!(merged.days, vec![day(3, "")]);
+        assert_eq!(merged.bookings, vec![booking(2)]);
+        assert_eq!(conflicts, 0);
+    }
+}
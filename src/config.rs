@@ -0,0 +1,271 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::Weekday;
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::app_common::colors;
+use crate::check::Rules;
+use crate::schedule::{default_work_days, Contract, DayHours, Schedule};
+use crate::vacation::Entitlement;
+use crate::work_day::Interval;
+
+/// Colors of the user interface
+pub struct Colors {
+    /// List mode, headers and highlights
+    pub accent: Color,
+    pub edit_move: Color,
+    pub edit_insert: Color,
+    pub help: Color,
+    pub stats: Color,
+}
+
+/// Effective settings, from the defaults, the global config file and the config file of the
+/// collection, where later ones override earlier ones
+pub struct Settings {
+    /// Weekly hours before the first contract period
+    pub week_hours: f32,
+    /// Work days before the first contract period
+    pub work_days: Vec<Weekday>,
+    /// Hours per weekday before the first contract period, replaces `week_hours` and
+    /// `work_days` if given
+    pub day_hours: DayHours,
+    /// Contract periods with different weekly hours or work days, see [`Schedule`]
+    pub contracts: Vec<Contract>,
+    /// Collection that is used if none is given on the command line
+    pub default_file: String,
+    /// Break of new entries, `None` for new entries without a break
+    pub default_break: Option<Interval>,
+    /// Format of dates in lists and statistics, see [`chrono::format::strftime`]
+    pub date_format: String,
+    /// Yearly vacation allowance, `None` if vacation is not tracked
+    pub vacation: Option<Entitlement>,
+    /// Thresholds of the working time checks
+    pub rules: Rules,
+    /// Number of backups kept of the collection, 0 to disable backups
+    pub backups: usize,
+    pub colors: Colors,
+}
+
+/// Contents of a config file, all settings are optional
+#[derive(Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    week_hours: Option<f32>,
+    work_days: Option<Vec<Weekday>>,
+    day_hours: Option<DayHours>,
+    default_file: Option<String>,
+    default_break: Option<String>,
+    date_format: Option<String>,
+    vacation: Option<Entitlement>,
+    rules: Option<Rules>,
+    backups: Option<usize>,
+    #[serde(default)]
+    colors: ColorConfig,
+    contracts: Option<Vec<Contract>>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColorConfig {
+    accent: Option<String>,
+    edit_move: Option<String>,
+    edit_insert: Option<String>,
+    help: Option<String>,
+    stats: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            week_hours: 39.5,
+            work_days: default_work_days(),
+            day_hours: DayHours::default(),
+            contracts: Vec::new(),
+            default_file: String::from("work_times.json"),
+            default_break: Some(Interval {
+                start: chrono::NaiveTime::from_hms_opt(11, 30, 0).unwrap(),
+                end: chrono::NaiveTime::from_hms_opt(12, 0, 0),
+                next_day: false,
+                start_next_day: false,
+            }),
+            date_format: String::from("%d.%m.%y"),
+            vacation: None,
+            rules: Rules::default(),
+            backups: 10,
+            colors: Colors {
+                accent: colors::ORANGE,
+                edit_move: colors::EDIT_MOVE_CLR,
+                edit_insert: colors::EDIT_INS_CLR,
+                help: colors::HELP_CLR,
+                stats: colors::STAT_CLR,
+            },
+        }
+    }
+}
+
+/// Path of the global config file, `$XDG_CONFIG_HOME/wd/config.toml` or
+/// `~/.config/wd/config.toml`
+pub fn global_config_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_home.join("wd").join("config.toml"))
+}
+
+/// Path of the config file of the collection at `file_path`, next to it with a `.toml`
+/// extension, e.g. `work_times.toml` for `work_times.json`
+pub fn collection_config_path(file_path: &str) -> PathBuf {
+    Path::new(file_path).with_extension("toml")
+}
+
+fn parse_color(name: &str, value: &str) -> Result<Color, String> {
+    Color::from_str(value).map_err(|_| format!("invalid color {value} for {name}"))
+}
+
+impl Settings {
+    /// Settings from the global config file, without the override of a collection
+    pub fn load_global() -> Result<Self, String> {
+        let mut settings = Self::default();
+        if let Some(path) = global_config_path() {
+            settings.apply_file(&path)?;
+        }
+        Ok(settings)
+    }
+
+    /// Applies the config file of the collection at `file_path`. Its `default_file` has no
+    /// effect, as the collection is already chosen.
+    pub fn load_collection(&mut self, file_path: &str) -> Result<(), String> {
+        self.apply_file(&collection_config_path(file_path))
+    }
+
+    /// Applies the config file at `path`, a missing file is not an error
+    fn apply_file(&mut self, path: &Path) -> Result<(), String> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(format!("Could not read config {}: {err}", path.display())),
+        };
+        let config = toml::from_str(&text)
+            .map_err(|err| format!("Could not parse config {}: {err}", path.display()))?;
+        self.apply(config)
+            .map_err(|err| format!("Invalid config {}: {err}", path.display()))
+    }
+
+    fn apply(&mut self, config: Config) -> Result<(), String> {
+        if let Some(week_hours) = config.week_hours {
+            self.week_hours = week_hours;
+        }
+        if let Some(work_days) = config.work_days {
+            self.work_days = work_days;
+        }
+        if let Some(day_hours) = config.day_hours {
+            self.day_hours = day_hours;
+        }
+        self.initial_contract().validate()?;
+        if let Some(contracts) = config.contracts {
+            for contract in &contracts {
+                let from = contract
+                    .from
+                    .ok_or(String::from("every contract needs a start date in from"))?;
+                contract
+                    .validate()
+                    .map_err(|err| format!("contract from {from}: {err}"))?;
+            }
+            self.contracts = contracts;
+        }
+        if let Some(default_file) = config.default_file {
+            self.default_file = default_file;
+        }
+        if let Some(default_break) = config.default_break {
+            self.default_break = if default_break.trim().is_empty() {
+                None
+            } else {
+                Some(
+                    default_break
+                        .parse()
+                        .map_err(|err| format!("invalid default_break: {err}"))?,
+                )
+            };
+        }
+        if let Some(date_format) = config.date_format {
+            if StrftimeItems::new(&date_format).any(|item| item == Item::Error) {
+                return Err(format!("invalid date_format {date_format}"));
+            }
+            self.date_format = date_format;
+        }
+        if let Some(vacation) = config.vacation {
+            vacation.validate()?;
+            self.vacation = Some(vacation);
+        }
+        if let Some(rules) = config.rules {
+            rules.validate()?;
+            self.rules = rules;
+        }
+        if let Some(backups) = config.backups {
+            self.backups = backups;
+        }
+
+        let colors = config.colors;
+        for (name, value, color) in [
+            ("accent", colors.accent, &mut self.colors.accent),
+            ("edit_move", colors.edit_move, &mut self.colors.edit_move),
+            (
+                "edit_insert",
+                colors.edit_insert,
+                &mut self.colors.edit_insert,
+            ),
+            ("help", colors.help, &mut self.colors.help),
+            ("stats", colors.stats, &mut self.colors.stats),
+        ] {
+            if let Some(value) = value {
+                *color = parse_color(name, &value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Contract before the first contract period
+    fn initial_contract(&self) -> Contract {
+        Contract {
+            from: None,
+            week_hours: Some(self.week_hours),
+            work_days: self.work_days.clone(),
+            day_hours: self.day_hours.clone(),
+        }
+    }
+
+    pub fn schedule(&self) -> Schedule {
+        Schedule::new(self.initial_contract(), &self.contracts)
+    }
+
+    /// The effective settings in the format of the config file
+    pub fn to_toml(&self) -> String {
+        let config = Config {
+            week_hours: Some(self.week_hours),
+            work_days: Some(self.work_days.clone()),
+            day_hours: Some(self.day_hours.clone()),
+            default_file: Some(self.default_file.clone()),
+            default_break: Some(
+                self.default_break
+                    .map_or(String::new(), |brk| brk.to_string()),
+            ),
+            date_format: Some(self.date_format.clone()),
+            vacation: self.vacation.clone(),
+            rules: Some(self.rules.clone()),
+            backups: Some(self.backups),
+            colors: ColorConfig {
+                accent: Some(self.colors.accent.to_string()),
+                edit_move: Some(self.colors.edit_move.to_string()),
+                edit_insert: Some(self.colors.edit_insert.to_string()),
+                help: Some(self.colors.help.to_string()),
+                stats: Some(self.colors.stats.to_string()),
+            },
+            contracts: Some(self.contracts.clone()),
+        };
+        toml::to_string(&config).unwrap()
+    }
+}
@@ -1,6 +1,34 @@
-use crate::app_common::colors::ORANGE;
-use crate::stat::StatUnit;
-use chrono::Duration;
+use crate::balance::Booking;
+use crate::config::Settings;
+use crate::stat::{DateRange, Period, StatUnit};
+use crate::vacation::VacationYear;
+use crate::work_day::WorkDay;
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+use std::io::Write;
+
+/// Output formats of `wd stat`
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum OutputFormat {
+    /// Coloured tables for the terminal
+    Text,
+    Json,
+    /// One row per period and a last row with the total
+    Csv,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(format!("unknown format {s}, expected text, json or csv")),
+        }
+    }
+}
 
 pub fn hm_from_duration(duration: Duration) -> String {
     let hours = duration.num_hours();
@@ -9,51 +37,371 @@ pub fn hm_from_duration(duration: Duration) -> String {
 }
 
 pub fn print_stat(
-    weeks: &[(chrono::NaiveDate, StatUnit)],
+    periods: &[(chrono::NaiveDate, StatUnit)],
+    period: Period,
     total: &StatUnit,
     employ_duration: &chrono::Duration,
+    vacation: Option<&VacationYear>,
+    settings: &Settings,
 ) -> Result<(), ()> {
     use crossterm::style::{Color, Stylize};
-    let orange = match ORANGE {
-        ratatui::style::Color::Rgb(r, g, b) => Color::Rgb { r, g, b },
-        _ => unreachable!(),
-    };
+    let accent = Color::from(settings.colors.accent);
 
+    let (start_name, end_name) = match period {
+        Period::Week => ("Week Start", "Week End"),
+        Period::Month => ("Month Start", "Month End"),
+        Period::Year => ("Year Start", "Year End"),
+    };
     println!(
         "{}",
         format!(
-            "{:12} {:10} {:7} {:11} {:9}",
-            "Week Start", "Week End", "Hours", "Active Days", "Sick Days"
+            "{:12} {:10} {:>8} {:>8} {:>8} {:11} {:9} {:13}",
+            start_name,
+            end_name,
+            "Target",
+            "Hours",
+            "Surplus",
+            "Active Days",
+            "Sick Days",
+            "Vacation Days"
         )
         .bold()
-        .with(orange)
+        .with(accent)
     );
 
-    println!("{}", "=".repeat(53));
-    for (week_start, stat) in weeks {
-        let week_end = week_start.week(chrono::Weekday::Mon).last_day();
+    println!("{}", "=".repeat(94));
+    for (start, stat) in periods {
         println!(
-            "{:12} {:10} {:7} {:11} {:9}",
-            week_start.format("%d.%m.%y"),
-            week_end.format("%d.%m.%y"),
+            "{:12} {:10} {:>8} {:>8} {:>8} {:11} {:9} {:13}",
+            start.format(&settings.date_format),
+            period.end(*start).format(&settings.date_format),
+            hm_from_duration(stat.target),
             hm_from_duration(stat.work),
+            signed_hm_from_duration(stat.surplus()),
             stat.active_days,
             stat.sick_days,
+            stat.vacation_days,
         );
     }
 
+    // at least one day, so a single recorded day does not divide by zero
     let avg_work_per_week = chrono::Duration::minutes(
-        (total.work.num_minutes() as f64 / (employ_duration.num_days() as f64 / 7.0)) as i64,
+        (total.work.num_minutes() as f64 / (employ_duration.num_days().max(1) as f64 / 7.0)) as i64,
     );
 
     println!(
         "{} {} (avg {} per week, not excluding sick days)",
-        "Total Time:".bold().with(orange),
+        "Total Time:".bold().with(accent),
         hm_from_duration(total.work),
         hm_from_duration(avg_work_per_week)
     );
+    println!(
+        "{} {} (surplus {})",
+        "Target Time:".bold().with(accent),
+        hm_from_duration(total.target),
+        signed_hm_from_duration(total.surplus())
+    );
 
-    println!("{} {}", "Sick Days:".bold().with(orange), total.sick_days);
+    println!("{} {}", "Sick Days:".bold().with(accent), total.sick_days);
+    println!(
+        "{} {}",
+        "Vacation Days:".bold().with(accent),
+        total.vacation_days
+    );
+    println!("{} {}", "Holidays:".bold().with(accent), total.holidays);
+    if let Some(vacation) = vacation {
+        let mut entitled = format!("{}", vacation.entitlement);
+        if vacation.carried != 0.0 {
+            entitled += &format!(" + {} carried over", vacation.carried);
+        }
+        if vacation.expired != 0.0 {
+            entitled += &format!(", {} expired", vacation.expired);
+        }
+        println!(
+            "{} {} taken / {} planned / {} remaining ({entitled})",
+            format!("Vacation {}:", vacation.year).bold().with(accent),
+            vacation.taken,
+            vacation.planned,
+            vacation.remaining(),
+        );
+    }
 
     return Ok(());
 }
+
+/// Statistics of a single period, or of all periods for the total
+#[derive(Serialize)]
+struct StatRow<'a> {
+    period: String,
+    first: NaiveDate,
+    last: NaiveDate,
+    surplus_minutes: i64,
+    #[serde(flatten)]
+    stat: &'a StatUnit,
+}
+
+impl<'a> StatRow<'a> {
+    fn new(period: String, first: NaiveDate, last: NaiveDate, stat: &'a StatUnit) -> Self {
+        Self {
+            period,
+            first,
+            last,
+            surplus_minutes: stat.surplus().num_minutes(),
+            stat,
+        }
+    }
+
+    const CSV_HEADER: [&'static str; 18] = [
+        "period",
+        "first",
+        "last",
+        "work_minutes",
+        "break_minutes",
+        "target_minutes",
+        "credited_minutes",
+        "surplus_minutes",
+        "num_days",
+        "active_days",
+        "home_office_days",
+        "sick_days",
+        "vacation_days",
+        "compensation_days",
+        "holidays",
+        "mean_start",
+        "mean_end",
+        "project_minutes",
+    ];
+
+    /// Fields in the order of [`Self::CSV_HEADER`], projects as `project=minutes` separated by
+    /// semicolons
+    fn csv_record(&self) -> [String; 18] {
+        let stat = self.stat;
+        let time = |time: Option<chrono::NaiveTime>| time.map_or(String::new(), |t| t.to_string());
+        [
+            self.period.clone(),
+            self.first.to_string(),
+            self.last.to_string(),
+            stat.work.num_minutes().to_string(),
+            stat.brk.num_minutes().to_string(),
+            stat.target.num_minutes().to_string(),
+            stat.credited.num_minutes().to_string(),
+            self.surplus_minutes.to_string(),
+            stat.num_days.to_string(),
+            stat.active_days.to_string(),
+            stat.home_office_days.to_string(),
+            stat.sick_days.to_string(),
+            stat.vacation_days.to_string(),
+            stat.compensation_days.to_string(),
+            stat.holidays.to_string(),
+            time(stat.mean_start),
+            time(stat.mean_end),
+            stat.projects
+                .iter()
+                .map(|(project, time)| format!("{project}={}", time.num_minutes()))
+                .collect::<Vec<_>>()
+                .join(";"),
+        ]
+    }
+}
+
+/// Rows of all periods and the total, which spans the `range` of the statistics or from the first
+/// to the last period without one
+fn stat_rows<'a>(
+    periods: &'a [(NaiveDate, StatUnit)],
+    period: Period,
+    range: Option<DateRange>,
+    total: &'a StatUnit,
+) -> (Vec<StatRow<'a>>, Option<StatRow<'a>>) {
+    let rows = periods
+        .iter()
+        .map(|(start, stat)| StatRow::new(period.to_string(), *start, period.end(*start), stat))
+        .collect::<Vec<_>>();
+    let total = match (range, rows.first(), rows.last()) {
+        (Some(range), Some(_), Some(_)) => Some(StatRow::new(
+            String::from("total"),
+            range.first,
+            range.last,
+            total,
+        )),
+        (None, Some(first), Some(last)) => Some(StatRow::new(
+            String::from("total"),
+            first.first,
+            last.last,
+            total,
+        )),
+        _ => None,
+    };
+    (rows, total)
+}
+
+/// Treats a closed pipe as the end of the output, e.g. of `wd stat --format json | head`
+fn ignore_broken_pipe(result: std::io::Result<()>) -> std::io::Result<()> {
+    match result {
+        Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
+/// Prints the statistics of each period and the total as a JSON object
+pub fn print_stat_json(
+    periods: &[(NaiveDate, StatUnit)],
+    period: Period,
+    range: Option<DateRange>,
+    total: &StatUnit,
+) -> Result<(), String> {
+    #[derive(Serialize)]
+    struct Report<'a> {
+        periods: Vec<StatRow<'a>>,
+        total: Option<StatRow<'a>>,
+    }
+
+    let (periods, total) = stat_rows(periods, period, range, total);
+    let json = serde_json::to_string_pretty(&Report { periods, total })
+        .map_err(|err| format!("Could not serialize statistics: {err}"))?;
+    ignore_broken_pipe(writeln!(std::io::stdout().lock(), "{json}"))
+        .map_err(|err| format!("Could not write statistics: {err}"))
+}
+
+/// Prints the statistics of each period and the total as CSV, see [`StatRow::CSV_HEADER`]
+pub fn print_stat_csv(
+    periods: &[(NaiveDate, StatUnit)],
+    period: Period,
+    range: Option<DateRange>,
+    total: &StatUnit,
+) -> Result<(), String> {
+    let (periods, total) = stat_rows(periods, period, range, total);
+    let mut writer = csv::Writer::from_writer(std::io::stdout().lock());
+    let mut write = || -> std::io::Result<()> {
+        writer.write_record(StatRow::CSV_HEADER)?;
+        for row in periods.iter().chain(total.iter()) {
+            writer.write_record(row.csv_record())?;
+        }
+        writer.flush()
+    };
+    ignore_broken_pipe(write()).map_err(|err| format!("Could not write statistics: {err}"))
+}
+
+/// Prints the worked time per project and all finished days whose project allocations do not add
+/// up to the worked time
+pub fn print_project_stat(days: &[WorkDay], total: &StatUnit, settings: &Settings) {
+    use crossterm::style::{Color, Stylize};
+    let accent = Color::from(settings.colors.accent);
+
+    println!(
+        "{}",
+        format!("{:20} {:>7}", "Project", "Hours")
+            .bold()
+            .with(accent)
+    );
+    println!("{}", "=".repeat(28));
+    for (project, time) in &total.projects {
+        println!("{:20} {:>7}", project, hm_from_duration(*time));
+    }
+    let finished = days.iter().filter(|day| !day.is_running());
+    println!(
+        "{:20} {:>7}",
+        "Unallocated",
+        signed_hm_from_duration(
+            finished
+                .clone()
+                .fold(Duration::zero(), |acc, day| acc + day.unallocated_time())
+        )
+    );
+
+    let mismatched = finished
+        .filter(|day| day.unallocated_time() != Duration::zero())
+        .collect::<Vec<_>>();
+    if mismatched.is_empty() {
+        return;
+    }
+
+    println!();
+    println!(
+        "{}",
+        format!(
+            "{:10} {:>7} {:>9} {:>11}",
+            "Date", "Worked", "Allocated", "Unallocated"
+        )
+        .bold()
+        .with(accent)
+    );
+    println!("{}", "=".repeat(40));
+    for day in mismatched {
+        println!(
+            "{:10} {:>7} {:>9} {:>11}",
+            day.date.format(&settings.date_format),
+            hm_from_duration(day.worked_time()),
+            hm_from_duration(day.worked_time() - day.unallocated_time()),
+            signed_hm_from_duration(day.unallocated_time()),
+        );
+    }
+}
+
+/// Prints the surplus and running flex balance of each week, the bookings and the final balance
+pub fn print_balance(
+    weeks: &[(chrono::NaiveDate, StatUnit)],
+    bookings: &[Booking],
+    balance: Duration,
+    settings: &Settings,
+) {
+    use crossterm::style::{Color, Stylize};
+    let accent = Color::from(settings.colors.accent);
+
+    println!(
+        "{}",
+        format!(
+            "{:12} {:>7} {:>7} {:>8} {:>8}",
+            "Week Start", "Target", "Hours", "Surplus", "Balance"
+        )
+        .bold()
+        .with(accent)
+    );
+    println!("{}", "=".repeat(46));
+    for (week_start, stat) in weeks {
+        println!(
+            "{:12} {:>7} {:>7} {:>8} {:>8}",
+            week_start.format(&settings.date_format),
+            hm_from_duration(stat.target),
+            hm_from_duration(stat.work),
+            signed_hm_from_duration(stat.surplus()),
+            signed_hm_from_duration(stat.balance),
+        );
+    }
+
+    if !bookings.is_empty() {
+        println!();
+        println!(
+            "{}",
+            format!("{:12} {:10} {:>8}  {}", "Date", "Booking", "Amount", "Note")
+                .bold()
+                .with(accent)
+        );
+        println!("{}", "=".repeat(46));
+        for booking in bookings {
+            println!(
+                "{:12} {:10} {:>8}  {}",
+                booking.date.format(&settings.date_format),
+                booking.kind.to_string(),
+                signed_hm_from_duration(booking.amount),
+                booking.note,
+            );
+        }
+    }
+
+    println!();
+    println!(
+        "{} {}",
+        "Flex Balance:".bold().with(accent),
+        signed_hm_from_duration(balance)
+    );
+}
+
+/// Like [`hm_from_duration`], but with a sign, also for negative durations
+pub fn signed_hm_from_duration(duration: Duration) -> String {
+    let sign = if duration < Duration::zero() {
+        "-"
+    } else {
+        "+"
+    };
+    format!("{sign}{}", hm_from_duration(duration.abs()))
+}
@@ -1,14 +1,24 @@
-use crate::work_day::{Break, DayType, WorkDay};
+use crate::disp_utils::hm_from_duration;
+use crate::work_day::{
+    parse_duration, parse_tags, Absence, AbsenceKind, Allocation, DayType, Interval, WorkDay,
+};
 use chrono::{NaiveDate, NaiveTime};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum EditField {
     Date,
-    Start,
-    End,
-    BreakStart,
-    BreakEnd,
+    Start(usize),
+    End(usize),
+    BreakStart(usize),
+    BreakEnd(usize),
     DayType,
+    HolidayName,
+    Project(usize),
+    Allocation(usize),
+    Absence,
+    AbsenceShare,
+    Note,
+    Tags,
 }
 
 #[derive(Copy, Clone)]
@@ -17,8 +27,10 @@ pub enum EditDayType {
     HomeOffice,
     Sick,
     Vacation,
-    Unofficial { has_break: bool },
+    Unofficial,
     Travel,
+    Compensation,
+    Holiday,
 }
 
 impl EditDayType {
@@ -27,11 +39,12 @@ impl EditDayType {
         match self {
             Present => Travel,
             Travel => HomeOffice,
-            HomeOffice => Unofficial { has_break: false },
-            Unofficial { has_break: false } => Unofficial { has_break: true },
-            Unofficial { has_break: true } => Sick,
+            HomeOffice => Unofficial,
+            Unofficial => Sick,
             Sick => Vacation,
-            Vacation => Present,
+            Vacation => Compensation,
+            Compensation => Holiday,
+            Holiday => Present,
         }
     }
 
@@ -40,258 +53,433 @@ impl EditDayType {
         match self {
             Travel => Present,
             HomeOffice => Travel,
-            Unofficial { has_break: false } => HomeOffice,
-            Unofficial { has_break: true } => Unofficial { has_break: false },
-            Sick => Unofficial { has_break: true },
+            Unofficial => HomeOffice,
+            Sick => Unofficial,
             Vacation => Sick,
-            Present => Vacation,
+            Holiday => Compensation,
+            Compensation => Vacation,
+            Present => Holiday,
         }
     }
-}
 
-impl EditField {
-    pub fn next(&self, day_type: EditDayType) -> Self {
-        use EditField::*;
-        match day_type {
-            EditDayType::Present | EditDayType::HomeOffice => match self {
-                Date => DayType,
-                DayType => Start,
-                Start => End,
-                End => BreakStart,
-                BreakStart => BreakEnd,
-                BreakEnd => Date,
-            },
-            EditDayType::Sick => match self {
-                Date => DayType,
-                DayType => Date,
-                Start | End | BreakStart | BreakEnd => unreachable!(),
-            },
-            EditDayType::Unofficial { has_break } => {
-                if has_break {
-                    match self {
-                        Date => DayType,
-                        DayType => Start,
-                        Start => End,
-                        End => BreakStart,
-                        BreakStart => BreakEnd,
-                        BreakEnd => Date,
-                    }
-                } else {
-                    match self {
-                        Date => DayType,
-                        DayType => Start,
-                        Start => End,
-                        End => Date,
-                        BreakStart | BreakEnd => unreachable!(),
-                    }
-                }
-            }
-            EditDayType::Vacation => match self {
-                Date => DayType,
-                DayType => Date,
-                Start | End | BreakStart | BreakEnd => unreachable!(),
-            },
-            EditDayType::Travel => match self {
-                Date => DayType,
-                DayType => Start,
-                Start => End,
-                End => Date,
-                BreakStart | BreakEnd => unreachable!(),
-            },
+    pub fn has_sessions(&self) -> bool {
+        match self {
+            EditDayType::Present
+            | EditDayType::HomeOffice
+            | EditDayType::Unofficial
+            | EditDayType::Travel => true,
+            EditDayType::Sick
+            | EditDayType::Vacation
+            | EditDayType::Compensation
+            | EditDayType::Holiday => false,
         }
     }
 
-    pub fn prev(&self, day_type: EditDayType) -> Self {
-        use EditField::*;
-        match day_type {
-            EditDayType::Present | EditDayType::HomeOffice => match self {
-                Date => BreakEnd,
-                DayType => Date,
-                Start => DayType,
-                End => Start,
-                BreakStart => End,
-                BreakEnd => BreakStart,
-            },
-            EditDayType::Sick => match self {
-                Date => DayType,
-                DayType => Date,
-                Start | End | BreakStart | BreakEnd => unreachable!(),
-            },
-            EditDayType::Unofficial { has_break } => {
-                if has_break {
-                    match self {
-                        Date => BreakEnd,
-                        DayType => Date,
-                        Start => DayType,
-                        End => Start,
-                        BreakStart => End,
-                        BreakEnd => BreakStart,
-                    }
-                } else {
-                    match self {
-                        Date => End,
-                        DayType => Date,
-                        Start => DayType,
-                        End => Start,
-                        BreakStart | BreakEnd => unreachable!(),
-                    }
-                }
-            }
-            EditDayType::Vacation => match self {
-                DayType => Date,
-                Date => DayType,
-                Start | End | BreakStart | BreakEnd => unreachable!(),
-            },
-            EditDayType::Travel => match self {
-                DayType => Date,
-                Start => DayType,
-                End => Start,
-                Date => End,
-                BreakStart | BreakEnd => unreachable!(),
-            },
+    pub fn has_breaks(&self) -> bool {
+        match self {
+            EditDayType::Present | EditDayType::HomeOffice | EditDayType::Unofficial => true,
+            EditDayType::Sick
+            | EditDayType::Vacation
+            | EditDayType::Compensation
+            | EditDayType::Travel
+            | EditDayType::Holiday => false,
         }
     }
 }
 
+/// Cycles the kind of a partial absence through none, vacation, sick leave and compensation
+pub fn next_absence(absence: Option<AbsenceKind>) -> Option<AbsenceKind> {
+    match absence {
+        None => Some(AbsenceKind::Vacation),
+        Some(AbsenceKind::Vacation) => Some(AbsenceKind::Sick),
+        Some(AbsenceKind::Sick) => Some(AbsenceKind::Compensation),
+        Some(AbsenceKind::Compensation) => None,
+    }
+}
+
+impl EditField {
+    pub fn next(&self, edit_bufs: &EditBufs) -> Self {
+        let fields = edit_bufs.fields();
+        let pos = fields.iter().position(|f| f == self).unwrap_or(0);
+        fields[(pos + 1) % fields.len()]
+    }
+
+    pub fn prev(&self, edit_bufs: &EditBufs) -> Self {
+        let fields = edit_bufs.fields();
+        let pos = fields.iter().position(|f| f == self).unwrap_or(0);
+        fields[(pos + fields.len() - 1) % fields.len()]
+    }
+}
+
+/// Edit buffers for a list of entries with two fields each, e.g. the start and end of intervals
+struct IntervalBufs {
+    bufs: Vec<[String; 2]>,
+}
+
+impl IntervalBufs {
+    fn new() -> Self {
+        Self { bufs: Vec::new() }
+    }
+
+    fn len(&self) -> usize {
+        self.bufs.len()
+    }
+
+    fn push(&mut self) -> usize {
+        self.bufs.push([String::new(), String::new()]);
+        self.bufs.len() - 1
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.bufs.remove(index);
+    }
+}
+
 pub struct EditBufs {
-    bufs: [[u8; 64]; 5],
-    cursors: [u8; 5],
+    date_buf: String,
+    sessions: IntervalBufs,
+    breaks: IntervalBufs,
+    allocations: IntervalBufs,
+    holiday_buf: String,
+    share_buf: String,
+    note_buf: String,
+    tags_buf: String,
     pub day_type: EditDayType,
+    pub absence: Option<AbsenceKind>,
 }
 
 impl EditBufs {
     fn new() -> Self {
         Self {
-            bufs: [[0; 64]; 5],
-            cursors: [0; 5],
+            date_buf: String::new(),
+            sessions: IntervalBufs::new(),
+            breaks: IntervalBufs::new(),
+            allocations: IntervalBufs::new(),
+            holiday_buf: String::new(),
+            share_buf: String::new(),
+            note_buf: String::new(),
+            tags_buf: String::new(),
             day_type: EditDayType::Present,
+            absence: None,
+        }
+    }
+
+    /// Empty buffers for a day of `day_type`, e.g. to fill them from another source than the
+    /// editor
+    pub fn with_day_type(day_type: EditDayType) -> Self {
+        Self {
+            day_type,
+            ..Self::new()
         }
     }
 
-    pub fn entry_mut(&mut self, index: EditField) -> (&mut [u8; 64], &mut u8) {
+    pub fn entry_mut(&mut self, index: EditField) -> &mut String {
         match index {
-            EditField::Date => (&mut self.bufs[0], &mut self.cursors[0]),
-            EditField::Start => (&mut self.bufs[1], &mut self.cursors[1]),
-            EditField::End => (&mut self.bufs[2], &mut self.cursors[2]),
-            EditField::BreakStart => (&mut self.bufs[3], &mut self.cursors[3]),
-            EditField::BreakEnd => (&mut self.bufs[4], &mut self.cursors[4]),
-            EditField::DayType => unreachable!(),
+            EditField::Date => &mut self.date_buf,
+            EditField::Start(i) => &mut self.sessions.bufs[i][0],
+            EditField::End(i) => &mut self.sessions.bufs[i][1],
+            EditField::BreakStart(i) => &mut self.breaks.bufs[i][0],
+            EditField::BreakEnd(i) => &mut self.breaks.bufs[i][1],
+            EditField::Project(i) => &mut self.allocations.bufs[i][0],
+            EditField::Allocation(i) => &mut self.allocations.bufs[i][1],
+            EditField::HolidayName => &mut self.holiday_buf,
+            EditField::AbsenceShare => &mut self.share_buf,
+            EditField::Note => &mut self.note_buf,
+            EditField::Tags => &mut self.tags_buf,
+            EditField::DayType | EditField::Absence => unreachable!(),
         }
     }
 
-    pub fn entry(&self, index: EditField) -> (&[u8; 64], &u8) {
+    pub fn entry(&self, index: EditField) -> &String {
         match index {
-            EditField::Date => (&self.bufs[0], &self.cursors[0]),
-            EditField::Start => (&self.bufs[1], &self.cursors[1]),
-            EditField::End => (&self.bufs[2], &self.cursors[2]),
-            EditField::BreakStart => (&self.bufs[3], &self.cursors[3]),
-            EditField::BreakEnd => (&self.bufs[4], &self.cursors[4]),
-            EditField::DayType => unreachable!(),
+            EditField::Date => &self.date_buf,
+            EditField::Start(i) => &self.sessions.bufs[i][0],
+            EditField::End(i) => &self.sessions.bufs[i][1],
+            EditField::BreakStart(i) => &self.breaks.bufs[i][0],
+            EditField::BreakEnd(i) => &self.breaks.bufs[i][1],
+            EditField::Project(i) => &self.allocations.bufs[i][0],
+            EditField::Allocation(i) => &self.allocations.bufs[i][1],
+            EditField::HolidayName => &self.holiday_buf,
+            EditField::AbsenceShare => &self.share_buf,
+            EditField::Note => &self.note_buf,
+            EditField::Tags => &self.tags_buf,
+            EditField::DayType | EditField::Absence => unreachable!(),
         }
     }
 
     pub fn text(&self, index: EditField) -> &str {
-        let (buf, cur) = self.entry(index);
-        std::str::from_utf8(&buf[..*cur as usize]).unwrap()
+        self.entry(index)
     }
-}
 
-impl From<&WorkDay> for EditBufs {
-    fn from(day: &WorkDay) -> Self {
-        use EditField as E;
-        let mut ret = Self::new();
-        ret.cursors[E::Date as usize] = ret[E::Date]
-            .iter_mut()
-            .zip(day.date.to_string().as_bytes())
-            .map(|(b, sb)| *b = *sb)
-            .count() as u8;
-        match &day.day_type {
-            DayType::Present {
-                start,
-                end,
-                brk:
-                    Break {
-                        start: break_start,
-                        end: break_end,
-                    },
+    /// All fields that can be edited for the current day type, in display order
+    pub fn fields(&self) -> Vec<EditField> {
+        let mut fields = vec![EditField::Date, EditField::DayType];
+        if let EditDayType::Holiday = self.day_type {
+            fields.push(EditField::HolidayName);
+        }
+        if self.day_type.has_sessions() {
+            for i in 0..self.num_sessions() {
+                fields.extend_from_slice(&[EditField::Start(i), EditField::End(i)]);
             }
-            | DayType::HomeOffice {
-                start,
-                end,
-                brk:
-                    Break {
-                        start: break_start,
-                        end: break_end,
-                    },
-            } => {
-                ret.cursors[E::Start as usize] = ret[E::Start]
-                    .iter_mut()
-                    .zip(start.format("%H:%M").to_string().as_bytes())
-                    .map(|(b, sb)| *b = *sb)
-                    .count() as u8;
-                ret.cursors[E::End as usize] = ret[E::End]
-                    .iter_mut()
-                    .zip(end.format("%H:%M").to_string().as_bytes())
-                    .map(|(b, sb)| *b = *sb)
-                    .count() as u8;
-                ret.cursors[E::BreakStart as usize] = ret[E::BreakStart]
-                    .iter_mut()
-                    .zip(break_start.format("%H:%M").to_string().as_bytes())
-                    .map(|(b, sb)| *b = *sb)
-                    .count() as u8;
-                ret.cursors[E::BreakEnd as usize] = ret[E::BreakEnd]
-                    .iter_mut()
-                    .zip(break_end.format("%H:%M").to_string().as_bytes())
-                    .map(|(b, sb)| *b = *sb)
-                    .count() as u8;
-                ret.day_type = match day.day_type {
-                    DayType::Present { .. } => EditDayType::Present,
-                    DayType::HomeOffice { .. } => EditDayType::HomeOffice,
-                    _ => unreachable!(),
-                }
+        }
+        if self.day_type.has_breaks() {
+            for i in 0..self.num_breaks() {
+                fields.extend_from_slice(&[EditField::BreakStart(i), EditField::BreakEnd(i)]);
             }
-            DayType::Sick => ret.day_type = EditDayType::Sick,
-            DayType::Unofficial { start, end, brk } => {
-                ret.cursors[E::Start as usize] = ret[E::Start]
-                    .iter_mut()
-                    .zip(start.format("%H:%M").to_string().as_bytes())
-                    .map(|(b, sb)| *b = *sb)
-                    .count() as u8;
-                ret.cursors[E::End as usize] = ret[E::End]
-                    .iter_mut()
-                    .zip(end.format("%H:%M").to_string().as_bytes())
-                    .map(|(b, sb)| *b = *sb)
-                    .count() as u8;
-                if let Some(b) = brk {
-                    ret.cursors[E::BreakStart as usize] = ret[E::BreakStart]
-                        .iter_mut()
-                        .zip(b.start.format("%H:%M").to_string().as_bytes())
-                        .map(|(b, sb)| *b = *sb)
-                        .count() as u8;
-                    ret.cursors[E::BreakEnd as usize] = ret[E::BreakEnd]
-                        .iter_mut()
-                        .zip(b.end.format("%H:%M").to_string().as_bytes())
-                        .map(|(b, sb)| *b = *sb)
-                        .count() as u8;
-                    ret.day_type = EditDayType::Unofficial { has_break: true };
+        }
+        if self.day_type.has_sessions() {
+            for i in 0..self.num_allocations() {
+                fields.extend_from_slice(&[EditField::Project(i), EditField::Allocation(i)]);
+            }
+            fields.push(EditField::Absence);
+            if self.absence.is_some() {
+                fields.push(EditField::AbsenceShare);
+            }
+        }
+        fields.extend_from_slice(&[EditField::Note, EditField::Tags]);
+        fields
+    }
+
+    pub fn num_sessions(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Appends an empty work session and returns its index
+    pub fn add_session(&mut self) -> usize {
+        self.sessions.push()
+    }
+
+    pub fn remove_session(&mut self, index: usize) {
+        self.sessions.remove(index);
+    }
+
+    pub fn num_breaks(&self) -> usize {
+        self.breaks.len()
+    }
+
+    /// Appends an empty break and returns its index
+    pub fn add_break(&mut self) -> usize {
+        self.breaks.push()
+    }
+
+    pub fn remove_break(&mut self, index: usize) {
+        self.breaks.remove(index);
+    }
+
+    pub fn num_allocations(&self) -> usize {
+        self.allocations.len()
+    }
+
+    /// Appends an empty project allocation and returns its index
+    pub fn add_allocation(&mut self) -> usize {
+        self.allocations.push()
+    }
+
+    pub fn remove_allocation(&mut self, index: usize) {
+        self.allocations.remove(index);
+    }
+
+    /// Switches to the next kind of partial absence, starting with half a day
+    pub fn cycle_absence(&mut self) {
+        self.absence = next_absence(self.absence);
+        if self.share_buf.is_empty() {
+            self.set_text(EditField::AbsenceShare, "0.5");
+        }
+    }
+
+    /// Sets the buffer of `index` to `text`
+    pub fn set_text(&mut self, index: EditField, text: &str) {
+        *self.entry_mut(index) = String::from(text);
+    }
+
+    fn fill_interval(&mut self, start: EditField, end: EditField, interval: &Interval) {
+        self.set_text(start, &interval.start_text());
+        if let Some(interval_end) = interval.end {
+            let next_day = if interval.next_day { "+1" } else { "" };
+            self.set_text(end, &format!("{}{next_day}", interval_end.format("%H:%M")));
+        }
+    }
+
+    /// Parses an interval, an empty end means the interval is still running and a start or end
+    /// with a `+1` suffix is on the next day
+    fn parse_interval(
+        &self,
+        start: EditField,
+        end: EditField,
+        name: &str,
+    ) -> Result<Interval, String> {
+        let (start_text, start_next_day) = match self.text(start).strip_suffix("+1") {
+            Some(start_text) => (start_text, true),
+            None => (self.text(start), false),
+        };
+        let (end_text, next_day) = match self.text(end).strip_suffix("+1") {
+            Some(end_text) => (end_text, true),
+            None => (self.text(end), false),
+        };
+        let interval = Interval {
+            start: NaiveTime::parse_from_str(start_text, "%H:%M")
+                .map_err(|err| format!("could not parse {name} Start: {err}"))?,
+            end: if end_text.is_empty() && !next_day {
+                None
+            } else {
+                Some(
+                    NaiveTime::parse_from_str(end_text, "%H:%M")
+                        .map_err(|err| format!("could not parse {name} End: {err}"))?,
+                )
+            },
+            next_day,
+            start_next_day,
+        };
+        interval.validate(name)?;
+        Ok(interval)
+    }
+
+    /// Parses all work session fields, only the last session may be running or end on the next
+    /// day
+    fn parse_sessions(&self) -> Result<Vec<Interval>, String> {
+        if self.num_sessions() == 0 {
+            return Err(String::from("at least one work session is required"));
+        }
+        let sessions = (0..self.num_sessions())
+            .map(|i| {
+                self.parse_interval(
+                    EditField::Start(i),
+                    EditField::End(i),
+                    &format!("Session {}", i + 1),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (i, pair) in sessions.windows(2).enumerate() {
+            match pair[0] {
+                Interval { end: None, .. } => {
+                    return Err(format!("Session {} is running but not the last", i + 1))
+                }
+                Interval { next_day: true, .. } => {
+                    return Err(format!(
+                        "Session {} ends on the next day but not the last",
+                        i + 1
+                    ))
                 }
-                ret.day_type = EditDayType::Unofficial { has_break: false };
+                Interval { end: Some(_), .. }
+                    if pair[1].offsets().0 < pair[0].offsets().1.unwrap() =>
+                {
+                    return Err(format!(
+                        "Session {} starts before session {} ends",
+                        i + 2,
+                        i + 1
+                    ))
+                }
+                _ => (),
+            }
+        }
+        Ok(sessions)
+    }
+
+    /// Parses the partial absence, given as share of the day
+    fn parse_absence(&self) -> Result<Option<Absence>, String> {
+        match self.absence {
+            None => Ok(None),
+            Some(kind) => {
+                let absence = Absence {
+                    kind,
+                    share: self
+                        .text(EditField::AbsenceShare)
+                        .parse()
+                        .map_err(|err| format!("could not parse Absence Share: {err}"))?,
+                };
+                absence.validate()?;
+                Ok(Some(absence))
             }
-            DayType::Travel { start, end } => {
-                ret.cursors[E::Start as usize] = ret[E::Start]
-                    .iter_mut()
-                    .zip(start.to_string().as_bytes())
-                    .map(|(b, sb)| *b = *sb)
-                    .count() as u8;
-                ret.cursors[E::End as usize] = ret[E::End]
-                    .iter_mut()
-                    .zip(end.to_string().as_bytes())
-                    .map(|(b, sb)| *b = *sb)
-                    .count() as u8;
-                ret.day_type = EditDayType::Travel
+        }
+    }
+
+    /// Parses all project allocations, the time is either a duration `HH:MM` or an interval
+    /// `HH:MM-HH:MM`
+    fn parse_allocations(&self) -> Result<Vec<Allocation>, String> {
+        (0..self.num_allocations())
+            .map(|i| {
+                let name = format!("Project {}", i + 1);
+                let project = self.text(EditField::Project(i)).trim();
+                if project.is_empty() {
+                    return Err(format!("{name} has no name"));
+                }
+                let time = self.text(EditField::Allocation(i)).trim();
+                Ok(if time.contains('-') {
+                    Allocation::Interval {
+                        project: String::from(project),
+                        interval: time
+                            .parse()
+                            .map_err(|err| format!("could not parse {name} Time: {err}"))?,
+                    }
+                } else {
+                    Allocation::Duration {
+                        project: String::from(project),
+                        duration: parse_duration(time)
+                            .map_err(|err| format!("could not parse {name} Time: {err}"))?,
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Parses all break fields
+    fn parse_breaks(&self) -> Result<Vec<Interval>, String> {
+        (0..self.num_breaks())
+            .map(|i| {
+                self.parse_interval(
+                    EditField::BreakStart(i),
+                    EditField::BreakEnd(i),
+                    &format!("Break {}", i + 1),
+                )
+            })
+            .collect()
+    }
+}
+
+impl From<&WorkDay> for EditBufs {
+    fn from(day: &WorkDay) -> Self {
+        let mut ret = Self::new();
+        ret.set_text(EditField::Date, &day.date.to_string());
+        ret.day_type = match &day.day_type {
+            DayType::Present { .. } => EditDayType::Present,
+            DayType::HomeOffice { .. } => EditDayType::HomeOffice,
+            DayType::Unofficial { .. } => EditDayType::Unofficial,
+            DayType::Travel { .. } => EditDayType::Travel,
+            DayType::Sick => EditDayType::Sick,
+            DayType::Vacation => EditDayType::Vacation,
+            DayType::Compensation => EditDayType::Compensation,
+            DayType::Holiday { name } => {
+                ret.set_text(EditField::HolidayName, name);
+                EditDayType::Holiday
             }
-            DayType::Vacation => ret.day_type = EditDayType::Vacation,
+        };
+        for session in day.sessions() {
+            let i = ret.add_session();
+            ret.fill_interval(EditField::Start(i), EditField::End(i), session);
+        }
+        for brk in day.breaks() {
+            let i = ret.add_break();
+            ret.fill_interval(EditField::BreakStart(i), EditField::BreakEnd(i), brk);
+        }
+        if let Some(absence) = day.absence {
+            ret.absence = Some(absence.kind);
+            ret.set_text(EditField::AbsenceShare, &absence.share.to_string());
+        }
+        for allocation in &day.allocations {
+            let i = ret.add_allocation();
+            ret.set_text(EditField::Project(i), allocation.project());
+            let time = match allocation {
+                Allocation::Duration { duration, .. } => hm_from_duration(*duration),
+                Allocation::Interval { interval, .. } => interval.to_string(),
+            };
+            ret.set_text(EditField::Allocation(i), &time);
         }
+        ret.set_text(EditField::Note, &day.note);
+        ret.set_text(
+            EditField::Tags,
+            &day.tags.iter().cloned().collect::<Vec<_>>().join(", "),
+        );
         ret
     }
 }
@@ -305,95 +493,54 @@ impl TryInto<WorkDay> for &EditBufs {
                 .map_err(|err| format!("could not parse Date: {err}"))?,
             day_type: match self.day_type {
                 EditDayType::Present => DayType::Present {
-                    start: NaiveTime::parse_from_str(self.text(EditField::Start), "%H:%M")
-                        .map_err(|err| format!("could not parse Start: {err}"))?,
-                    end: NaiveTime::parse_from_str(self.text(EditField::End), "%H:%M")
-                        .map_err(|err| format!("could not parse End: {err}"))?,
-                    brk: Break {
-                        start: NaiveTime::parse_from_str(
-                            self.text(EditField::BreakStart),
-                            "%H:%M",
-                        )
-                        .map_err(|err| format!("could not parse Break Start: {err}"))?,
-                        end: NaiveTime::parse_from_str(self.text(EditField::BreakEnd), "%H:%M")
-                            .map_err(|err| format!("could not parse Break End {err}"))?,
-                    },
+                    sessions: self.parse_sessions()?,
+                    breaks: self.parse_breaks()?,
                 },
                 EditDayType::HomeOffice => DayType::HomeOffice {
-                    start: NaiveTime::parse_from_str(self.text(EditField::Start), "%H:%M")
-                        .map_err(|err| format!("could not parse Start: {err}"))?,
-                    end: NaiveTime::parse_from_str(self.text(EditField::End), "%H:%M")
-                        .map_err(|err| format!("could not parse End: {err}"))?,
-                    brk: Break {
-                        start: NaiveTime::parse_from_str(
-                            self.text(EditField::BreakStart),
-                            "%H:%M",
-                        )
-                        .map_err(|err| format!("could not parse Break Start: {err}"))?,
-                        end: NaiveTime::parse_from_str(self.text(EditField::BreakEnd), "%H:%M")
-                            .map_err(|err| format!("could not parse Break End {err}"))?,
-                    },
+                    sessions: self.parse_sessions()?,
+                    breaks: self.parse_breaks()?,
                 },
                 EditDayType::Sick => DayType::Sick,
-                EditDayType::Unofficial { has_break } => DayType::Unofficial {
-                    start: NaiveTime::parse_from_str(self.text(EditField::Start), "%H:%M")
-                        .map_err(|err| format!("could not parse Start: {err}"))?,
-                    end: NaiveTime::parse_from_str(self.text(EditField::End), "%H:%M")
-                        .map_err(|err| format!("could not parse End: {err}"))?,
-                    brk: if has_break {
-                        Some(Break {
-                            start: NaiveTime::parse_from_str(
-                                self.text(EditField::BreakStart),
-                                "%H:%M",
-                            )
-                            .map_err(|err| format!("could not parse Break Start: {err}"))?,
-                            end: NaiveTime::parse_from_str(
-                                self.text(EditField::BreakEnd),
-                                "%H:%M",
-                            )
-                            .map_err(|err| format!("could not parse Break End {err}"))?,
-                        })
-                    } else {
-                        None
-                    },
+                EditDayType::Unofficial => DayType::Unofficial {
+                    sessions: self.parse_sessions()?,
+                    breaks: self.parse_breaks()?,
                 },
                 EditDayType::Vacation => DayType::Vacation,
+                EditDayType::Compensation => DayType::Compensation,
                 EditDayType::Travel => DayType::Travel {
-                    start: NaiveTime::parse_from_str(self.text(EditField::Start), "%H:%M:%S")
-                        .map_err(|err| format!("could not parse Start: {err}"))?,
-                    end: NaiveTime::parse_from_str(self.text(EditField::End), "%H:%M:%S")
-                        .map_err(|err| format!("could not parse End: {err}"))?,
+                    sessions: self.parse_sessions()?,
                 },
+                EditDayType::Holiday => DayType::Holiday {
+                    name: String::from(self.text(EditField::HolidayName)),
+                },
+            },
+            absence: if self.day_type.has_sessions() {
+                self.parse_absence()?
+            } else {
+                None
+            },
+            note: String::from(self.text(EditField::Note).trim()),
+            tags: parse_tags(self.text(EditField::Tags)),
+            allocations: if self.day_type.has_sessions() {
+                self.parse_allocations()?
+            } else {
+                Vec::new()
             },
         })
     }
 }
 
 impl std::ops::Index<EditField> for EditBufs {
-    type Output = [u8; 64];
+    type Output = String;
 
     fn index(&self, index: EditField) -> &Self::Output {
-        match index {
-            EditField::Date => &self.bufs[0],
-            EditField::Start => &self.bufs[1],
-            EditField::End => &self.bufs[2],
-            EditField::BreakStart => &self.bufs[3],
-            EditField::BreakEnd => &self.bufs[4],
-            EditField::DayType => unreachable!(),
-        }
+        self.entry(index)
     }
 }
 
 impl std::ops::IndexMut<EditField> for EditBufs {
     fn index_mut(&mut self, index: EditField) -> &mut Self::Output {
-        match index {
-            EditField::Date => &mut self.bufs[0],
-            EditField::Start => &mut self.bufs[1],
-            EditField::End => &mut self.bufs[2],
-            EditField::BreakStart => &mut self.bufs[3],
-            EditField::BreakEnd => &mut self.bufs[4],
-            EditField::DayType => unreachable!(),
-        }
+        self.entry_mut(index)
     }
 }
 
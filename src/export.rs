@@ -0,0 +1,595 @@
+use std::collections::BTreeSet;
+use std::io::{Read, Write};
+
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use ics::properties::{Categories, Description, DtEnd, DtStart, Summary};
+use ics::{escape_text, Event, ICalendar};
+
+use crate::disp_utils::hm_from_duration;
+use crate::editor::{EditBufs, EditDayType, EditField};
+use crate::work_day::{AbsenceKind, Allocation, DayType, Interval, WorkDay};
+
+/// Formats of `wd export`
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ExportFormat {
+    /// One row per day with the columns [`CSV_COLUMNS`]
+    Csv,
+    /// Calendar events, see [`write_ics`]
+    Ics,
+}
+
+impl ExportFormat {
+    /// Day types exported if none are selected, all for CSV and the absences for calendars
+    pub fn default_types(&self) -> Vec<TypeName> {
+        let names: &[&'static str] = match self {
+            ExportFormat::Csv => &DAY_TYPES.map(|(name, _)| name),
+            ExportFormat::Ics => &["vacation", "sick", "travel"],
+        };
+        names.iter().map(|name| TypeName(name)).collect()
+    }
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "ics" => Ok(ExportFormat::Ics),
+            _ => Err(format!("unknown format {s}, expected csv or ics")),
+        }
+    }
+}
+
+/// Columns of the CSV format, on import only `date` and `type` are required and the order is
+/// free
+pub const CSV_COLUMNS: [&str; 10] = [
+    "date",
+    "type",
+    "sessions",
+    "breaks",
+    "holiday",
+    "absence",
+    "absence_share",
+    "projects",
+    "note",
+    "tags",
+];
+
+/// Names of the day types in the `type` column, the same as in the JSON collection
+const DAY_TYPES: [(&str, EditDayType); 8] = [
+    ("present", EditDayType::Present),
+    ("homeoffice", EditDayType::HomeOffice),
+    ("unofficial", EditDayType::Unofficial),
+    ("travel", EditDayType::Travel),
+    ("sick", EditDayType::Sick),
+    ("vacation", EditDayType::Vacation),
+    ("compensation", EditDayType::Compensation),
+    ("holiday", EditDayType::Holiday),
+];
+
+/// Name of a day type as in the `type` column, e.g. to select the days to export
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TypeName(&'static str);
+
+impl TypeName {
+    pub fn of(day_type: &DayType) -> Self {
+        TypeName(match day_type {
+            DayType::Present { .. } => "present",
+            DayType::HomeOffice { .. } => "homeoffice",
+            DayType::Unofficial { .. } => "unofficial",
+            DayType::Travel { .. } => "travel",
+            DayType::Sick => "sick",
+            DayType::Vacation => "vacation",
+            DayType::Compensation => "compensation",
+            DayType::Holiday { .. } => "holiday",
+        })
+    }
+}
+
+impl std::str::FromStr for TypeName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DAY_TYPES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+            .map(|(name, _)| TypeName(name))
+            .ok_or(format!(
+                "unknown type {s}, expected one of {}",
+                DAY_TYPES.map(|(name, _)| name).join(", ")
+            ))
+    }
+}
+
+impl std::fmt::Display for TypeName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Names of the partial absences in the `absence` column
+const ABSENCES: [(&str, AbsenceKind); 3] = [
+    ("vacation", AbsenceKind::Vacation),
+    ("sick", AbsenceKind::Sick),
+    ("compensation", AbsenceKind::Compensation),
+];
+
+/// Intervals separated by spaces, a running interval has no end, e.g. `08:00-12:00 12:30-`
+fn intervals_text(intervals: &[Interval]) -> String {
+    intervals
+        .iter()
+        .map(|interval| match interval.end {
+            Some(_) => interval.to_string(),
+            None => format!("{}-", interval.start_text()),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn csv_record(day: &WorkDay) -> [String; 10] {
+    let holiday = match &day.day_type {
+        DayType::Holiday { name } => name.as_str(),
+        _ => "",
+    };
+    let (absence, share) = match day.absence {
+        Some(absence) => {
+            let (name, _) = ABSENCES
+                .iter()
+                .find(|(_, kind)| *kind == absence.kind)
+                .unwrap();
+            (*name, absence.share.to_string())
+        }
+        None => ("", String::new()),
+    };
+    let projects = day
+        .allocations
+        .iter()
+        .map(|allocation| match allocation {
+            Allocation::Duration { project, duration } => {
+                format!("{project}={}", hm_from_duration(*duration))
+            }
+            Allocation::Interval { project, interval } => format!("{project}={interval}"),
+        })
+        .collect::<Vec<_>>()
+        .join(";");
+    [
+        day.date.to_string(),
+        TypeName::of(&day.day_type).to_string(),
+        intervals_text(day.sessions()),
+        intervals_text(day.breaks()),
+        String::from(holiday),
+        String::from(absence),
+        share,
+        projects,
+        day.note.clone(),
+        day.tags.iter().cloned().collect::<Vec<_>>().join(", "),
+    ]
+}
+
+/// Writes `days` as CSV with a header of [`CSV_COLUMNS`]
+pub fn write_csv<W: Write>(days: &[WorkDay], writer: W) -> Result<(), String> {
+    let mut writer = csv::Writer::from_writer(writer);
+    let write_err = |err: csv::Error| format!("Could not write CSV: {err}");
+    writer.write_record(CSV_COLUMNS).map_err(write_err)?;
+    for day in days {
+        writer.write_record(csv_record(day)).map_err(write_err)?;
+    }
+    writer
+        .flush()
+        .map_err(|err| format!("Could not write CSV: {err}"))
+}
+
+/// Title of the events of `day`, e.g. `Vacation` or `Alice: Vacation` with an `owner`
+fn event_summary(day: &WorkDay, owner: Option<&str>) -> String {
+    let name = match &day.day_type {
+        DayType::Present { .. } => "Present",
+        DayType::HomeOffice { .. } => "Home Office",
+        DayType::Unofficial { .. } => "Unofficial",
+        DayType::Travel { .. } => "Travel",
+        DayType::Sick => "Sick",
+        DayType::Vacation => "Vacation",
+        DayType::Compensation => "Compensation",
+        DayType::Holiday { name } if !name.is_empty() => name,
+        DayType::Holiday { .. } => "Holiday",
+    };
+    match owner {
+        Some(owner) => format!("{owner}: {name}"),
+        None => String::from(name),
+    }
+}
+
+fn ics_date_time(date_time: NaiveDateTime) -> String {
+    date_time.format("%Y%m%dT%H%M%S").to_string()
+}
+
+/// Writes `days` as calendar events. Days with work sessions get a timed event per finished
+/// session, all other days and travel days an all-day event. The UIDs are made of `uid_base`, the
+/// date and the number of the session, so a calendar updates the events of an earlier export.
+/// Event titles start with `owner` if it is given.
+pub fn write_ics<W: Write>(
+    days: &[WorkDay],
+    uid_base: &str,
+    owner: Option<&str>,
+    writer: W,
+) -> Result<(), String> {
+    let uid_base = uid_base.split_whitespace().collect::<Vec<_>>().join("-");
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut calendar = ICalendar::new("2.0", "-//wd//work days//EN");
+    for day in days {
+        let mut events = Vec::new();
+        let uid = format!("{uid_base}-{}", day.date.format("%Y%m%d"));
+        match &day.day_type {
+            DayType::Present { sessions, .. }
+            | DayType::HomeOffice { sessions, .. }
+            | DayType::Unofficial { sessions, .. } => {
+                for (i, session) in sessions.iter().enumerate() {
+                    let Some(end) = session.end else {
+                        continue;
+                    };
+                    let mut event = Event::new(format!("{uid}-{}@wd", i + 1), stamp.clone());
+                    event.push(DtStart::new(ics_date_time(
+                        day.date.and_time(session.start) + session.start_offset(),
+                    )));
+                    event.push(DtEnd::new(ics_date_time(
+                        day.date.and_time(end) + session.day_offset(),
+                    )));
+                    events.push(event);
+                }
+            }
+            DayType::Travel { .. }
+            | DayType::Sick
+            | DayType::Vacation
+            | DayType::Compensation
+            | DayType::Holiday { .. } => {
+                let mut event = Event::new(format!("{uid}@wd"), stamp.clone());
+                let mut start = DtStart::new(day.date.format("%Y%m%d").to_string());
+                start.add(ics::parameters::Value::new("DATE"));
+                event.push(start);
+                let mut end = DtEnd::new(day.date.succ_opt().unwrap().format("%Y%m%d").to_string());
+                end.add(ics::parameters::Value::new("DATE"));
+                event.push(end);
+                events.push(event);
+            }
+        }
+        for mut event in events {
+            event.push(Summary::new(escape_text(event_summary(day, owner))));
+            if !day.note.is_empty() {
+                event.push(Description::new(escape_text(day.note.clone())));
+            }
+            if !day.tags.is_empty() {
+                let tags = day.tags.iter().map(|tag| escape_text(tag.clone()));
+                event.push(Categories::new(tags.collect::<Vec<_>>().join(",")));
+            }
+            calendar.add_event(event);
+        }
+    }
+    calendar
+        .write(writer)
+        .map_err(|err| format!("Could not write calendar: {err}"))
+}
+
+/// Value of column `name` in `row`
+fn value<'a>(row: &[&'a str; 10], name: &str) -> &'a str {
+    row[CSV_COLUMNS
+        .iter()
+        .position(|column| *column == name)
+        .unwrap()]
+}
+
+/// Splits an interval `HH:MM-HH:MM` into start and end, the end may be empty for a running
+/// interval
+fn split_interval(text: &str) -> Result<(&str, &str), String> {
+    text.split_once('-')
+        .ok_or(format!("expected an interval as HH:MM-HH:MM, got {text}"))
+}
+
+/// Parses one row like an entry of the editor
+fn parse_row(row: &[&str; 10]) -> Result<WorkDay, String> {
+    let type_name = value(row, "type");
+    let (_, day_type) = DAY_TYPES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(type_name))
+        .ok_or(format!(
+            "unknown type {type_name}, expected one of {}",
+            DAY_TYPES.map(|(name, _)| name).join(", ")
+        ))?;
+
+    for (column, allowed) in [
+        ("sessions", day_type.has_sessions()),
+        ("breaks", day_type.has_breaks()),
+        ("holiday", matches!(day_type, EditDayType::Holiday)),
+        ("absence", day_type.has_sessions()),
+        ("absence_share", day_type.has_sessions()),
+        ("projects", day_type.has_sessions()),
+    ] {
+        if !allowed && !value(row, column).is_empty() {
+            return Err(format!(
+                "{column} must be empty on a day of type {type_name}"
+            ));
+        }
+    }
+
+    let mut bufs = EditBufs::with_day_type(*day_type);
+    bufs.set_text(EditField::Date, value(row, "date"));
+    bufs.set_text(EditField::HolidayName, value(row, "holiday"));
+    for session in value(row, "sessions").split_whitespace() {
+        let (start, end) = split_interval(session)?;
+        let i = bufs.add_session();
+        bufs.set_text(EditField::Start(i), start);
+        bufs.set_text(EditField::End(i), end);
+    }
+    for brk in value(row, "breaks").split_whitespace() {
+        let (start, end) = split_interval(brk)?;
+        let i = bufs.add_break();
+        bufs.set_text(EditField::BreakStart(i), start);
+        bufs.set_text(EditField::BreakEnd(i), end);
+    }
+    match value(row, "absence") {
+        "" if !value(row, "absence_share").is_empty() => {
+            return Err(String::from("absence_share is given without an absence"))
+        }
+        "" => (),
+        absence => {
+            let (_, kind) = ABSENCES
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(absence))
+                .ok_or(format!(
+                    "unknown absence {absence}, expected one of {}",
+                    ABSENCES.map(|(name, _)| name).join(", ")
+                ))?;
+            bufs.absence = Some(*kind);
+            bufs.set_text(EditField::AbsenceShare, value(row, "absence_share"));
+        }
+    }
+    for allocation in value(row, "projects")
+        .split(';')
+        .map(str::trim)
+        .filter(|allocation| !allocation.is_empty())
+    {
+        let (project, time) = allocation.rsplit_once('=').ok_or(format!(
+            "expected a project as NAME=HH:MM or NAME=HH:MM-HH:MM, got {allocation}"
+        ))?;
+        let i = bufs.add_allocation();
+        bufs.set_text(EditField::Project(i), project);
+        bufs.set_text(EditField::Allocation(i), time);
+    }
+
+    bufs.set_text(EditField::Note, value(row, "note"));
+    bufs.set_text(EditField::Tags, value(row, "tags"));
+    (&bufs).try_into()
+}
+
+/// Reads days from CSV with a header of [`CSV_COLUMNS`], missing fields at the end of a row are
+/// empty. Rows are validated like entries of the
+/// editor, rows on dates that already have an entry in `days` or an earlier row are rejected.
+/// Returns the errors of all rows with their line numbers if any row is invalid.
+pub fn read_csv<R: Read>(reader: R, days: &[WorkDay]) -> Result<Vec<WorkDay>, Vec<String>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(reader);
+    let header = reader
+        .headers()
+        .map_err(|err| vec![format!("could not read header: {err}")])?
+        .clone();
+    let mut columns = [None; 10];
+    for (i, name) in header.iter().enumerate() {
+        let column = CSV_COLUMNS
+            .iter()
+            .position(|column| column.eq_ignore_ascii_case(name))
+            .ok_or(vec![format!(
+                "line 1: unknown column {name}, expected {}",
+                CSV_COLUMNS.join(", ")
+            )])?;
+        columns[column] = Some(i);
+    }
+    for required in ["date", "type"] {
+        if columns[CSV_COLUMNS
+            .iter()
+            .position(|column| *column == required)
+            .unwrap()]
+        .is_none()
+        {
+            return Err(vec![format!("line 1: missing column {required}")]);
+        }
+    }
+
+    let mut dates = days
+        .iter()
+        .map(|day| day.date)
+        .collect::<BTreeSet<NaiveDate>>();
+    let mut imported = Vec::new();
+    let mut errors = Vec::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                let line = err.position().map_or(0, |position| position.line());
+                errors.push(format!("line {line}: {err}"));
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, |position| position.line());
+        if record.len() > header.len() {
+            errors.push(format!(
+                "line {line}: {} fields but only {} columns",
+                record.len(),
+                header.len()
+            ));
+            continue;
+        }
+        let row = std::array::from_fn(|column| {
+            columns[column]
+                .and_then(|i| record.get(i))
+                .unwrap_or_default()
+        });
+        match parse_row(&row) {
+            Ok(day) if !dates.insert(day.date) => errors.push(format!(
+                "line {line}: there already is an entry on {}",
+                day.date
+            )),
+            Ok(day) => imported.push(day),
+            Err(err) => errors.push(format!("line {line}: {err}")),
+        }
+    }
+    if errors.is_empty() {
+        Ok(imported)
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveTime};
+
+    use super::*;
+    use crate::work_day::{parse_tags, Absence};
+
+    fn interval(start: (u32, u32), end: (u32, u32), next_day: bool) -> Interval {
+        Interval {
+            start: NaiveTime::from_hms_opt(start.0, start.1, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(end.0, end.1, 0),
+            next_day,
+            start_next_day: false,
+        }
+    }
+
+    fn day(date: &str, day_type: DayType) -> WorkDay {
+        WorkDay::new(date.parse().unwrap(), day_type)
+    }
+
+    fn parse_record(record: &[String; 10]) -> Result<WorkDay, String> {
+        parse_row(&std::array::from_fn(|i| record[i].as_str()))
+    }
+
+    #[test]
+    fn row_round_trip() {
+        let days = [
+            WorkDay {
+                absence: Some(Absence {
+                    kind: AbsenceKind::Vacation,
+                    share: 0.5,
+                }),
+                note: String::from("doctor at 10, then \"home\""),
+                tags: parse_tags("release, oncall"),
+                allocations: vec![
+                    Allocation::Duration {
+                        project: String::from("alpha"),
+                        duration: Duration::minutes(150),
+                    },
+                    Allocation::Interval {
+                        project: String::from("beta"),
+                        interval: interval((13, 0), (15, 0), false),
+                    },
+                ],
+                ..day(
+                    "2025-03-03",
+                    DayType::Present {
+                        sessions: vec![
+                            interval((8, 0), (12, 0), false),
+                            interval((13, 0), (17, 0), false),
+                        ],
+                        breaks: vec![interval((14, 0), (14, 15), false)],
+                    },
+                )
+            },
+            day(
+                "2025-03-04",
+                DayType::HomeOffice {
+                    sessions: vec![interval((22, 0), (6, 0), true)],
+                    breaks: vec![Interval {
+                        start_next_day: true,
+                        ..interval((1, 0), (1, 30), true)
+                    }],
+                },
+            ),
+            day(
+                "2025-03-05",
+                DayType::Travel {
+                    sessions: vec![interval((6, 0), (20, 0), false)],
+                },
+            ),
+            day(
+                "2025-03-06",
+                DayType::Holiday {
+                    name: String::from("Tag der Arbeit"),
+                },
+            ),
+            day("2025-03-07", DayType::Compensation),
+        ];
+        for day in days {
+            assert_eq!(parse_record(&csv_record(&day)), Ok(day));
+        }
+    }
+
+    #[test]
+    fn running_session() {
+        let running = day(
+            "2025-03-03",
+            DayType::Present {
+                sessions: vec![Interval {
+                    end: None,
+                    ..interval((8, 0), (0, 0), false)
+                }],
+                breaks: Vec::new(),
+            },
+        );
+        let record = csv_record(&running);
+        assert_eq!(record[2], "08:00-");
+        assert_eq!(parse_record(&record), Ok(running));
+    }
+
+    #[test]
+    fn invalid_rows() {
+        let row = |fields: [&'static str; 4]| {
+            let mut row = [""; 10];
+            row[..4].copy_from_slice(&fields);
+            parse_row(&row)
+        };
+        assert!(row(["2025-03-03", "weekend", "", ""]).is_err());
+        assert!(row(["2025-03-03", "vacation", "08:00-12:00", ""]).is_err());
+        assert!(row(["2025-03-03", "present", "08:00", ""]).is_err());
+        assert!(row(["2025-03-03", "present", "12:00-08:00", ""]).is_err());
+        assert!(row(["2025-13-03", "sick", "", ""]).is_err());
+        assert_eq!(
+            row(["2025-03-03", "Vacation", "", ""]),
+            Ok(day("2025-03-03", DayType::Vacation))
+        );
+    }
+
+    #[test]
+    fn read_written_csv() {
+        let days = vec![
+            day(
+                "2025-03-03",
+                DayType::Unofficial {
+                    sessions: vec![interval((8, 0), (16, 30), false)],
+                    breaks: vec![interval((12, 0), (12, 30), false)],
+                },
+            ),
+            day("2025-03-04", DayType::Sick),
+        ];
+        let mut csv = Vec::new();
+        write_csv(&days, &mut csv).unwrap();
+        assert_eq!(read_csv(csv.as_slice(), &[]), Ok(days.clone()));
+
+        let errors = read_csv(csv.as_slice(), &days[1..]).unwrap_err();
+        assert_eq!(
+            errors,
+            vec!["line 3: there already is an entry on 2025-03-04"]
+        );
+    }
+
+    #[test]
+    fn read_columns_in_any_order() {
+        let csv = "type,date\nvacation,2025-03-03\nsick,2025-03-04,extra\n";
+        let errors = read_csv(csv.as_bytes(), &[]).unwrap_err();
+        assert_eq!(errors, vec!["line 3: 3 fields but only 2 columns"]);
+        assert_eq!(
+            read_csv("type,date\nvacation,2025-03-03\n".as_bytes(), &[]),
+            Ok(vec![day("2025-03-03", DayType::Vacation)])
+        );
+    }
+}
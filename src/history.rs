@@ -0,0 +1,233 @@
+use crate::work_day::WorkDay;
+
+/// Change of the days in the app that can be undone
+pub enum Change {
+    Add(WorkDay),
+    Remove(WorkDay),
+    Edit { before: WorkDay, after: WorkDay },
+}
+
+impl Change {
+    /// Change that reverts this one
+    fn inverse(self) -> Change {
+        match self {
+            Change::Add(day) => Change::Remove(day),
+            Change::Remove(day) => Change::Add(day),
+            Change::Edit { before, after } => Change::Edit {
+                before: after,
+                after: before,
+            },
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Change::Add(day) => format!("adding the entry of {}", day.date),
+            Change::Remove(day) => format!("removing the entry of {}", day.date),
+            Change::Edit { before, .. } => format!("editing the entry of {}", before.date),
+        }
+    }
+
+    /// Applies the change to `days`, which are kept sorted by date. Returns the index of the added
+    /// or edited entry, or of the one after the removed entry.
+    fn apply(&self, days: &mut Vec<WorkDay>) -> Result<usize, String> {
+        match self {
+            Change::Add(day) => {
+                days.push(day.clone());
+                days.sort_by_key(|day| day.date);
+                position(days, day)
+            }
+            Change::Remove(day) => {
+                let index = position(days, day)?;
+                days.remove(index);
+                Ok(index)
+            }
+            Change::Edit { before, after } => {
+                let index = position(days, before)?;
+                days[index] = after.clone();
+                days.sort_by_key(|day| day.date);
+                position(days, after)
+            }
+        }
+    }
+}
+
+/// Index of `day` in `days`, entries are found by their contents since the list is sorted after
+/// every change. Of several equal entries, e.g. a holiday added twice, the first one is taken. This
+/// leaves the same entries as taking the one that was changed, only their order on that date may
+/// differ.
+fn position(days: &[WorkDay], day: &WorkDay) -> Result<usize, String> {
+    days.iter()
+        .position(|other| other == day)
+        .ok_or_else(|| format!("The entry of {} is not in the list anymore", day.date))
+}
+
+/// Undo and redo stacks of the changes to the days, kept for the whole session
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Change>,
+    redo: Vec<Change>,
+}
+
+impl History {
+    /// Records a change that was just made, it can not be redone after undoing anymore
+    pub fn record(&mut self, change: Change) {
+        self.undo.push(change);
+        self.redo.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Reverts the last change to `days`, returns what was undone and the index of the affected
+    /// entry, `None` if there is nothing to undo. A change that can not be undone, e.g. because
+    /// the collection was merged with the one on disk, is dropped.
+    pub fn undo(&mut self, days: &mut Vec<WorkDay>) -> Option<Result<(String, usize), String>> {
+        let change = self.undo.pop()?;
+        let description = change.describe();
+        let inverse = change.inverse();
+        Some(inverse.apply(days).map(|index| {
+            self.redo.push(inverse.inverse());
+            (format!("Undid {description}"), index)
+        }))
+    }
+
+    /// Applies the last undone change to `days` again, like [`History::undo`]
+    pub fn redo(&mut self, days: &mut Vec<WorkDay>) -> Option<Result<(String, usize), String>> {
+        let change = self.redo.pop()?;
+        Some(change.apply(days).map(|index| {
+            let description = format!("Redid {}", change.describe());
+            self.undo.push(change);
+            (description, index)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::work_day::DayType;
+
+    fn day(day: u32, day_type: DayType) -> WorkDay {
+        WorkDay::new(NaiveDate::from_ymd_opt(2025, 3, day).unwrap(), day_type)
+    }
+
+    fn holiday(day_of_month: u32) -> WorkDay {
+        day(
+            day_of_month,
+            DayType::Holiday {
+                name: String::from("Holiday"),
+            },
+        )
+    }
+
+    /// Undoes and redoes the last change, checking the days against `before` and `after` it
+    fn undo_redo(history: &mut History, days: &mut Vec<WorkDay>, before: &[WorkDay]) {
+        let after = days.clone();
+        let (undone, _) = history.undo(days).unwrap().unwrap();
+        assert!(undone.starts_with("Undid"));
+        assert_eq!(days, before);
+        let (redone, _) = history.redo(days).unwrap().unwrap();
+        assert!(redone.starts_with("Redid"));
+        assert_eq!(*days, after);
+    }
+
+    #[test]
+    fn add() {
+        let mut history = History::default();
+        let mut days = vec![day(3, DayType::Sick), day(5, DayType::Sick)];
+        let before = days.clone();
+        days.insert(1, day(4, DayType::Vacation));
+        history.record(Change::Add(day(4, DayType::Vacation)));
+        undo_redo(&mut history, &mut days, &before);
+    }
+
+    #[test]
+    fn remove() {
+        let mut history = History::default();
+        let mut days = vec![day(3, DayType::Sick), day(4, DayType::Vacation)];
+        let before = days.clone();
+        history.record(Change::Remove(days.remove(0)));
+        undo_redo(&mut history, &mut days, &before);
+        assert_eq!(
+            history.undo(&mut days).unwrap(),
+            Ok((String::from("Undid removing the entry of 2025-03-03"), 0))
+        );
+    }
+
+    #[test]
+    fn edit() {
+        let mut history = History::default();
+        let mut days = vec![day(3, DayType::Sick), day(4, DayType::Vacation)];
+        let before = days.clone();
+        // the date is changed, so the entry moves to the end
+        let after = day(5, DayType::Compensation);
+        let edited = std::mem::replace(&mut days[0], after.clone());
+        days.sort_by_key(|day| day.date);
+        history.record(Change::Edit {
+            before: edited,
+            after,
+        });
+        undo_redo(&mut history, &mut days, &before);
+        assert_eq!(
+            history.undo(&mut days).unwrap().map(|(_, index)| index),
+            Ok(0)
+        );
+    }
+
+    #[test]
+    fn nothing_to_undo_or_redo() {
+        let mut history = History::default();
+        let mut days = vec![day(3, DayType::Sick)];
+        assert!(history.undo(&mut days).is_none());
+        assert!(history.redo(&mut days).is_none());
+
+        history.record(Change::Remove(day(4, DayType::Sick)));
+        history.undo(&mut days).unwrap().unwrap();
+        // a new change can not be followed by redoing an older one
+        history.record(Change::Add(day(5, DayType::Sick)));
+        assert!(history.redo(&mut days).is_none());
+    }
+
+    #[test]
+    fn entry_not_in_list_anymore() {
+        let mut history = History::default();
+        let mut days = vec![day(3, DayType::Sick)];
+        history.record(Change::Add(day(4, DayType::Sick)));
+        // e.g. removed by a merge with the collection on disk
+        assert_eq!(
+            history.undo(&mut days),
+            Some(Err(String::from(
+                "The entry of 2025-03-04 is not in the list anymore"
+            )))
+        );
+        assert_eq!(days, vec![day(3, DayType::Sick)]);
+        assert!(history.undo(&mut days).is_none());
+    }
+
+    #[test]
+    fn equal_entries() {
+        let mut history = History::default();
+        let mut days = vec![holiday(3), holiday(3), day(4, DayType::Sick)];
+        let before = days.clone();
+        // the second of the equal holidays is removed, undo and redo take the first one
+        history.record(Change::Remove(days.remove(1)));
+        undo_redo(&mut history, &mut days, &before);
+        assert_eq!(days, vec![holiday(3), day(4, DayType::Sick)]);
+
+        let edited = std::mem::replace(&mut days[0], holiday(4));
+        days.sort_by_key(|day| day.date);
+        history.record(Change::Edit {
+            before: edited,
+            after: holiday(4),
+        });
+        days.push(holiday(4));
+        // both holidays on the 4th are equal, undoing the edit changes the first one
+        history.undo(&mut days).unwrap().unwrap();
+        assert_eq!(days, vec![holiday(3), day(4, DayType::Sick), holiday(4)]);
+    }
+}
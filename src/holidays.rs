@@ -0,0 +1,268 @@
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+use crate::schedule::Schedule;
+use crate::work_day::{DayType, WorkDay};
+
+/// German federal states, which differ in their public holidays
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum State {
+    BadenWuerttemberg,
+    Bayern,
+    Berlin,
+    Brandenburg,
+    Bremen,
+    Hamburg,
+    Hessen,
+    MecklenburgVorpommern,
+    Niedersachsen,
+    NordrheinWestfalen,
+    RheinlandPfalz,
+    Saarland,
+    Sachsen,
+    SachsenAnhalt,
+    SchleswigHolstein,
+    Thueringen,
+}
+
+impl State {
+    const CODES: [(&'static str, State); 16] = [
+        ("BW", State::BadenWuerttemberg),
+        ("BY", State::Bayern),
+        ("BE", State::Berlin),
+        ("BB", State::Brandenburg),
+        ("HB", State::Bremen),
+        ("HH", State::Hamburg),
+        ("HE", State::Hessen),
+        ("MV", State::MecklenburgVorpommern),
+        ("NI", State::Niedersachsen),
+        ("NW", State::NordrheinWestfalen),
+        ("RP", State::RheinlandPfalz),
+        ("SL", State::Saarland),
+        ("SN", State::Sachsen),
+        ("ST", State::SachsenAnhalt),
+        ("SH", State::SchleswigHolstein),
+        ("TH", State::Thueringen),
+    ];
+}
+
+impl std::str::FromStr for State {
+    type Err = String;
+
+    /// Parses the two letter code of a state, e.g. `BY` for Bayern
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        State::CODES
+            .iter()
+            .find(|(code, _)| code.eq_ignore_ascii_case(s))
+            .map(|(_, state)| *state)
+            .ok_or(format!(
+                "unknown state {s}, expected one of {}",
+                State::CODES.map(|(code, _)| code).join(", ")
+            ))
+    }
+}
+
+/// Date of easter sunday in the gregorian calendar, using the anonymous gregorian algorithm
+pub fn easter_sunday(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).unwrap()
+}
+
+/// Public holidays of `year` with their names, sorted by date. Without a state, only the
+/// holidays common to all states are returned.
+pub fn holidays(year: i32, state: Option<State>) -> Vec<(NaiveDate, &'static str)> {
+    use State::*;
+
+    let date = |month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    let easter = easter_sunday(year);
+    let in_state = |states: &[State]| state.is_some_and(|state| states.contains(&state));
+
+    let mut ret = vec![
+        (date(1, 1), "Neujahr"),
+        (easter - Duration::days(2), "Karfreitag"),
+        (easter + Duration::days(1), "Ostermontag"),
+        (date(5, 1), "Tag der Arbeit"),
+        (easter + Duration::days(39), "Christi Himmelfahrt"),
+        (easter + Duration::days(50), "Pfingstmontag"),
+        (date(10, 3), "Tag der Deutschen Einheit"),
+        (date(12, 25), "1. Weihnachtstag"),
+        (date(12, 26), "2. Weihnachtstag"),
+    ];
+
+    if in_state(&[BadenWuerttemberg, Bayern, SachsenAnhalt]) {
+        ret.push((date(1, 6), "Heilige Drei Könige"));
+    }
+    if (year >= 2019 && in_state(&[Berlin])) || (year >= 2023 && in_state(&[MecklenburgVorpommern]))
+    {
+        ret.push((date(3, 8), "Internationaler Frauentag"));
+    }
+    if in_state(&[Brandenburg]) {
+        ret.push((easter, "Ostersonntag"));
+        ret.push((easter + Duration::days(49), "Pfingstsonntag"));
+    }
+    if in_state(&[
+        BadenWuerttemberg,
+        Bayern,
+        Hessen,
+        NordrheinWestfalen,
+        RheinlandPfalz,
+        Saarland,
+    ]) {
+        ret.push((easter + Duration::days(60), "Fronleichnam"));
+    }
+    if in_state(&[Saarland]) {
+        ret.push((date(8, 15), "Mariä Himmelfahrt"));
+    }
+    if year >= 2019 && in_state(&[Thueringen]) {
+        ret.push((date(9, 20), "Weltkindertag"));
+    }
+    // the 500th anniversary of the reformation in 2017 was a holiday in all states
+    if year == 2017
+        || in_state(&[
+            Brandenburg,
+            MecklenburgVorpommern,
+            Sachsen,
+            SachsenAnhalt,
+            Thueringen,
+        ])
+        || (year >= 2018 && in_state(&[Bremen, Hamburg, Niedersachsen, SchleswigHolstein]))
+    {
+        ret.push((date(10, 31), "Reformationstag"));
+    }
+    if in_state(&[
+        BadenWuerttemberg,
+        Bayern,
+        NordrheinWestfalen,
+        RheinlandPfalz,
+        Saarland,
+    ]) {
+        ret.push((date(11, 1), "Allerheiligen"));
+    }
+    if in_state(&[Sachsen]) {
+        // wednesday before the 23rd of november
+        let mut bettag = date(11, 22);
+        while bettag.weekday() != Weekday::Wed {
+            bettag = bettag.pred_opt().unwrap();
+        }
+        ret.push((bettag, "Buß- und Bettag"));
+    }
+
+    ret.sort_by_key(|(date, _)| *date);
+    ret
+}
+
+/// Adds the public holidays of `year` that fall on days with a target in `schedule` to `days`,
+/// skipping dates that already have an entry. Returns the added holidays.
+pub fn prefill(
+    days: &mut Vec<WorkDay>,
+    schedule: &Schedule,
+    year: i32,
+    state: Option<State>,
+) -> Vec<(NaiveDate, &'static str)> {
+    let added = holidays(year, state)
+        .into_iter()
+        .filter(|(date, _)| schedule.daily_target(*date) > Duration::zero())
+        .filter(|(date, _)| !days.iter().any(|day| day.date == *date))
+        .collect::<Vec<_>>();
+
+    days.extend(added.iter().map(|(date, name)| {
+        WorkDay::new(
+            *date,
+            DayType::Holiday {
+                name: String::from(*name),
+            },
+        )
+    }));
+    days.sort_by_key(|day| day.date);
+    added
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn easter_dates() {
+        assert_eq!(easter_sunday(2000), date(2000, 4, 23));
+        assert_eq!(easter_sunday(2019), date(2019, 4, 21));
+        assert_eq!(easter_sunday(2024), date(2024, 3, 31));
+        assert_eq!(easter_sunday(2025), date(2025, 4, 20));
+        // earliest and latest possible dates
+        assert_eq!(easter_sunday(2285), date(2285, 3, 22));
+        assert_eq!(easter_sunday(2038), date(2038, 4, 25));
+    }
+
+    #[test]
+    fn common_holidays() {
+        let holidays = holidays(2025, None);
+        assert_eq!(holidays.len(), 9);
+        assert!(holidays.contains(&(date(2025, 4, 18), "Karfreitag")));
+        assert!(holidays.contains(&(date(2025, 5, 29), "Christi Himmelfahrt")));
+        assert!(holidays.contains(&(date(2025, 6, 9), "Pfingstmontag")));
+        assert!(holidays.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn state_holidays() {
+        let bayern = holidays(2025, Some(State::Bayern));
+        assert_eq!(bayern.len(), 12);
+        assert!(bayern.contains(&(date(2025, 1, 6), "Heilige Drei Könige")));
+        assert!(bayern.contains(&(date(2025, 6, 19), "Fronleichnam")));
+        assert!(bayern.contains(&(date(2025, 11, 1), "Allerheiligen")));
+
+        let sachsen = holidays(2024, Some(State::Sachsen));
+        assert!(sachsen.contains(&(date(2024, 11, 20), "Buß- und Bettag")));
+        assert!(sachsen.contains(&(date(2024, 10, 31), "Reformationstag")));
+
+        let berlin = |year| holidays(year, Some(State::Berlin));
+        assert!(berlin(2019).contains(&(date(2019, 3, 8), "Internationaler Frauentag")));
+        assert!(!berlin(2018)
+            .iter()
+            .any(|(_, name)| *name == "Internationaler Frauentag"));
+    }
+
+    #[test]
+    fn reformation_anniversary() {
+        assert!(
+            holidays(2017, Some(State::Bayern)).contains(&(date(2017, 10, 31), "Reformationstag"))
+        );
+        assert!(!holidays(2018, Some(State::Bayern))
+            .iter()
+            .any(|(_, name)| *name == "Reformationstag"));
+    }
+
+    #[test]
+    fn prefill_on_work_days() {
+        let mut days = vec![WorkDay::new(date(2021, 1, 1), DayType::Vacation)];
+        // new year, may day, german unity day and christmas fall on the weekend or are taken
+        let added = prefill(&mut days, &Settings::default().schedule(), 2021, None);
+        assert_eq!(
+            added.iter().map(|(date, _)| *date).collect::<Vec<_>>(),
+            vec![
+                date(2021, 4, 2),
+                date(2021, 4, 5),
+                date(2021, 5, 13),
+                date(2021, 5, 24)
+            ]
+        );
+        assert_eq!(days.len(), 5);
+        assert_eq!(days[0].day_type, DayType::Vacation);
+    }
+}
@@ -1,6 +1,19 @@
-pub mod work_day;
-pub mod editor;
 pub mod app;
-pub mod stat;
-pub mod disp_utils;
 pub mod app_common;
+pub mod backup;
+pub mod balance;
+pub mod check;
+pub mod collection;
+pub mod config;
+pub mod disp_utils;
+pub mod editor;
+pub mod export;
+pub mod history;
+pub mod holidays;
+pub mod lock;
+pub mod punch;
+pub mod schedule;
+pub mod stat;
+pub mod storage;
+pub mod vacation;
+pub mod work_day;
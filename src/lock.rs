@@ -0,0 +1,39 @@
+use std::fs::{File, OpenOptions};
+use std::path::PathBuf;
+
+use fs2::FileExt;
+
+/// Advisory lock on a collection while it is open in the app, it is released when dropped
+pub struct Lock {
+    _file: File,
+}
+
+/// Lock file of the collection at `file_path`, next to it with a `.lock` suffix, e.g.
+/// `work_times.json.lock`. It is kept after the lock is released.
+pub fn lock_path(file_path: &str) -> PathBuf {
+    let mut path = PathBuf::from(file_path).into_os_string();
+    path.push(".lock");
+    PathBuf::from(path)
+}
+
+impl Lock {
+    /// Locks the collection at `file_path`, fails if it is already open in another instance of the
+    /// app. Commands on the command line do not take the lock, their changes are noticed when the
+    /// app writes the collection.
+    pub fn acquire(file_path: &str) -> Result<Self, String> {
+        let path = lock_path(file_path);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .map_err(|err| format!("Could not open lock file {}: {err}", path.display()))?;
+        match file.try_lock_exclusive() {
+            Ok(()) => Ok(Self { _file: file }),
+            Err(err) if err.kind() == fs2::lock_contended_error().kind() => Err(format!(
+                "{file_path} is already open in another wd, close it there first"
+            )),
+            Err(err) => Err(format!("Could not lock {}: {err}", path.display())),
+        }
+    }
+}
@@ -1,7 +1,8 @@
+use std::collections::HashMap;
 use std::io::{stdout, Stdout};
 
+use chrono::{Datelike, Local, NaiveDate, NaiveTime, Timelike};
 use clap::{Parser, Subcommand};
-use serde_json;
 
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
@@ -10,18 +11,45 @@ use crossterm::ExecutableCommand;
 use ratatui::prelude::{CrosstermBackend, Terminal};
 
 use wd::app::{events::handle_events, render::render_application};
-use wd::app::{AppMode, AppState, Message, Settings};
-use wd::disp_utils::print_stat;
+use wd::app::{AppMode, AppState, Message};
+use wd::balance::{check_booking, parse_signed_duration, running_balance, Booking, BookingKind};
+use wd::collection::Collection;
+use wd::config::{collection_config_path, global_config_path, Settings};
+use wd::disp_utils::{
+    print_balance, print_project_stat, print_stat, print_stat_csv, print_stat_json, OutputFormat,
+};
+use wd::export::{read_csv, write_csv, write_ics, ExportFormat, TypeName};
+use wd::history::History;
+use wd::holidays::State;
+use wd::lock::Lock;
+use wd::stat::{has_target, DateRange, Period};
+use wd::storage::{Backend, Storage};
 use wd::work_day::WorkDay;
 
-fn load_days(file_path: &str) -> Result<Vec<WorkDay>, ()> {
-    let days = std::fs::read_to_string(file_path).map_err(|err| {
-        eprintln!("Could not read file {file_path}: {err}");
-    })?;
+/// Upgrades a collection of an older format, returns a note about the upgrade
+fn upgrade_collection(storage: &mut dyn Storage, file_path: &str) -> Result<Option<String>, ()> {
+    let upgraded = storage.upgrade().map_err(|err| eprintln!("{err}"))?;
+    Ok(upgraded.map(|(old, new)| {
+        format!(
+            "Upgraded {file_path} from format version {old} to {new}, the old file is in {}",
+            wd::backup::backup_dir(file_path).display()
+        )
+    }))
+}
+
+fn load_collection(file_path: &str) -> Result<Collection, ()> {
+    let mut storage = wd::storage::open(file_path);
+    if let Some(note) = upgrade_collection(&mut *storage, file_path)? {
+        eprintln!("{note}");
+    }
+    storage.load().map_err(|err| eprintln!("{err}"))
+}
 
-    serde_json::from_str(&days).map_err(|err| {
-        eprintln!("Error during parsing of data: {err}");
-    })
+/// Saves `collection` after a backup of the previous state, keeping the newest `backups`
+fn save_collection(file_path: &str, collection: &Collection, backups: usize) -> Result<(), ()> {
+    wd::backup::rotate(file_path, backups)
+        .and_then(|()| wd::storage::open(file_path).save(&collection.days, &collection.bookings))
+        .map_err(|err| eprintln!("{err}"))
 }
 
 fn init_terminal() -> Result<Terminal<CrosstermBackend<Stdout>>, ()> {
@@ -60,7 +88,197 @@ enum Action {
     Create,
     /// Show statistics for collection
     #[command(name = "stat")]
-    Stat,
+    Stat {
+        /// Only include the work of days with this tag, the targets cover all days
+        #[arg(long)]
+        tag: Option<String>,
+        /// Show the worked time per project and days with mismatching allocations
+        #[arg(long, conflicts_with = "format")]
+        by_project: bool,
+        /// Output as text, json or csv
+        #[arg(long, default_value = "text")]
+        format: OutputFormat,
+        /// Group the statistics by week, month or year
+        #[arg(long, default_value = "week")]
+        period: Period,
+        #[command(flatten)]
+        range: RangeArgs,
+    },
+    /// Start today's work day at the current time
+    #[command(name = "start")]
+    Start,
+    /// Stop today's running work day at the current time
+    #[command(name = "stop")]
+    Stop,
+    /// Start or end a break in today's running work day
+    #[command(name = "break")]
+    Break {
+        #[command(subcommand)]
+        action: BreakAction,
+    },
+    /// Add the public holidays of a year to the collection
+    #[command(name = "holidays")]
+    Holidays {
+        year: i32,
+        /// Two letter code of the german state, e.g. BY. Without a state, only nationwide
+        /// holidays are added
+        #[arg(short, long)]
+        state: Option<State>,
+    },
+    /// Print the effective settings and the config files they are read from
+    #[command(name = "config")]
+    Config,
+    /// Check the work days against the working time rules, exits with 1 on violations
+    #[command(name = "check")]
+    Check,
+    /// Show the running flex time balance per week and the bookings on it
+    #[command(name = "balance")]
+    Balance {
+        #[command(subcommand)]
+        action: Option<BalanceAction>,
+    },
+    /// Export the work days, see the README for the columns of the CSV format
+    #[command(name = "export")]
+    Export {
+        /// Output format, csv or ics
+        #[arg(long, default_value = "csv")]
+        format: ExportFormat,
+        /// File to write to, defaults to stdout
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Only export days of these types, e.g. vacation,sick. Defaults to all types for csv and
+        /// to vacation, sick and travel for ics
+        #[arg(long = "type", value_delimiter = ',')]
+        types: Vec<TypeName>,
+        /// Name in the event titles and UIDs of ics exports, the UIDs default to the file name of
+        /// the collection
+        #[arg(long)]
+        owner: Option<String>,
+    },
+    /// List the backups of the collection or roll it back to one of them
+    #[command(name = "restore")]
+    Restore {
+        /// Number of the backup to restore as listed, 1 is the newest
+        backup: Option<usize>,
+    },
+    /// Rewrite a collection of an older format in the current one, after a backup. Older
+    /// collections are also upgraded when they are opened
+    #[command(name = "upgrade")]
+    Upgrade,
+    /// Copy the collection to another storage, e.g. from JSON to SQLite
+    #[command(name = "migrate")]
+    Migrate {
+        /// Storage to migrate to, json or sqlite
+        #[arg(long)]
+        to: Backend,
+        /// New collection file, defaults to the current one with the extension of the storage
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Add the work days of a CSV file in the format of `wd export` to the collection
+    #[command(name = "import")]
+    Import {
+        /// CSV file to read
+        csv_file: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum BalanceAction {
+    /// Book on the flex time account
+    #[command(name = "book")]
+    Book {
+        /// One of opening, correction, payout or reset
+        kind: BookingKind,
+        /// Amount as HH:MM, corrections may be negative, e.g. -02:30
+        #[arg(allow_hyphen_values = true)]
+        amount: String,
+        /// Date of the booking as YYYY-MM-DD, defaults to the first entry for an opening and to
+        /// today otherwise
+        #[arg(short, long)]
+        date: Option<NaiveDate>,
+        /// Reason for the booking
+        #[arg(short, long, default_value = "")]
+        note: String,
+    },
+}
+
+/// Date range of the statistics, the whole collection by default
+#[derive(clap::Args)]
+struct RangeArgs {
+    /// First day as YYYY-MM-DD, defaults to the first entry
+    #[arg(long)]
+    from: Option<NaiveDate>,
+    /// Last day as YYYY-MM-DD, defaults to the last entry
+    #[arg(long)]
+    to: Option<NaiveDate>,
+    /// The week before the current one
+    #[arg(long, conflicts_with_all = ["from", "to", "this_month", "year"])]
+    last_week: bool,
+    /// The current month up to today
+    #[arg(long, conflicts_with_all = ["from", "to", "year"])]
+    this_month: bool,
+    /// A whole year, the current one up to today
+    #[arg(long, conflicts_with_all = ["from", "to"])]
+    year: Option<i32>,
+}
+
+impl RangeArgs {
+    /// The selected range, `None` if none is selected. Open ends of `--from` and `--to` are the
+    /// first or last of the sorted `days`.
+    fn range(&self, days: &[WorkDay], today: NaiveDate) -> Result<Option<DateRange>, String> {
+        let range = if self.last_week {
+            let first = today.week(chrono::Weekday::Mon).first_day() - chrono::Duration::days(7);
+            DateRange {
+                first,
+                last: first + chrono::Duration::days(6),
+            }
+        } else if self.this_month {
+            DateRange {
+                first: today.with_day(1).unwrap(),
+                last: today,
+            }
+        } else if let Some(year) = self.year {
+            let first = NaiveDate::from_yo_opt(year, 1).ok_or(format!("invalid year {year}"))?;
+            let last = NaiveDate::from_ymd_opt(year, 12, 31).unwrap();
+            DateRange {
+                first,
+                last: if last > today && first <= today {
+                    today
+                } else {
+                    last
+                },
+            }
+        } else if self.from.is_some() || self.to.is_some() {
+            let (Some(first_day), Some(last_day)) = (days.first(), days.last()) else {
+                return Ok(None);
+            };
+            DateRange {
+                first: self.from.unwrap_or(first_day.date),
+                last: self.to.unwrap_or(last_day.date),
+            }
+        } else {
+            return Ok(None);
+        };
+
+        if range.first > range.last {
+            return Err(format!(
+                "the range from {} to {} is empty",
+                range.first, range.last
+            ));
+        }
+        Ok(Some(range))
+    }
+}
+
+#[derive(Subcommand)]
+enum BreakAction {
+    /// Start a break at the current time
+    #[command(name = "start")]
+    Start,
+    /// End the running break at the current time
+    #[command(name = "end")]
+    End,
 }
 
 #[derive(Parser)]
@@ -74,8 +292,8 @@ enum Action {
 struct Args {
     #[command(subcommand)]
     action: Option<Action>,
-    #[arg(default_value = "work_times.json")]
-    file_path: String,
+    /// Collection to use, defaults to `default_file` of the config or work_times.json
+    file_path: Option<String>,
 }
 
 fn tui_loop(mut state: AppState) -> Result<(), ()> {
@@ -98,60 +316,403 @@ fn tui_loop(mut state: AppState) -> Result<(), ()> {
     Ok(())
 }
 
+/// Applies a punch clock action to the collection at the current time and writes it back
+fn punch(
+    file_path: &str,
+    backups: usize,
+    action: fn(&mut Vec<WorkDay>, NaiveDate, NaiveTime) -> Result<String, String>,
+) -> Result<(), ()> {
+    let mut collection = load_collection(file_path)?;
+    let now = Local::now().naive_local();
+    let time = NaiveTime::from_hms_opt(now.hour(), now.minute(), 0).unwrap();
+
+    let msg = action(&mut collection.days, now.date(), time).map_err(|err| eprintln!("{err}"))?;
+    save_collection(file_path, &collection, backups)?;
+    println!("{msg}");
+    Ok(())
+}
+
 fn main() -> Result<(), ()> {
     let args = Args::parse();
+    let mut settings = Settings::load_global().map_err(|err| eprintln!("{err}"))?;
+    let file_path = args
+        .file_path
+        .unwrap_or_else(|| settings.default_file.clone());
+    settings
+        .load_collection(&file_path)
+        .map_err(|err| eprintln!("{err}"))?;
+
     match args.action {
         Some(Action::Open) | None => {
-            let mut days = load_days(&args.file_path)?;
+            let _lock = Lock::acquire(&file_path).map_err(|err| eprintln!("{err}"))?;
+            let mut storage = wd::storage::open(&file_path);
+            let upgraded = upgrade_collection(&mut *storage, &file_path)?;
+            let saved = storage.load().map_err(|err| eprintln!("{err}"))?;
+            let Collection { mut days, bookings } = saved.clone();
             days.sort_by_key(|day| day.date);
-            let state = AppState {
-                selected: Some(days.len() - 1),
-                message: Message::Info(format!(
-                    "Loaded {len} entries from {path}",
-                    len = days.len(),
-                    path = args.file_path
-                )),
-                settings: Settings { week_hours: 39.5 },
-                file_path: args.file_path,
+            let mut state = AppState {
+                selected: days.len().checked_sub(1),
+                message: Message::Info(upgraded.unwrap_or_else(|| {
+                    format!(
+                        "Loaded {len} entries from {path}",
+                        len = days.len(),
+                        path = file_path
+                    )
+                })),
+                schedule: settings.schedule(),
+                violations: HashMap::new(),
+                settings,
+                storage,
+                file_path,
                 days,
+                bookings,
+                saved,
+                history: History::default(),
                 mode: AppMode::ListOnly,
                 help_popup: None,
                 statistics: None,
             };
+            state.days_changed();
             tui_loop(state)
         }
         Some(Action::Create) => {
-            let state = AppState {
+            let _lock = Lock::acquire(&file_path).map_err(|err| eprintln!("{err}"))?;
+            let mut state = AppState {
                 selected: None,
                 message: Message::Info(format!(
                     "Created new collection with save path {path}",
-                    path = args.file_path
+                    path = file_path
                 )),
-                settings: Settings { week_hours: 39.5 },
-                file_path: args.file_path,
+                schedule: settings.schedule(),
+                violations: HashMap::new(),
+                settings,
+                storage: wd::storage::open(&file_path),
+                file_path,
                 days: Vec::new(),
+                bookings: Vec::new(),
+                saved: Collection::default(),
+                history: History::default(),
                 mode: AppMode::ListOnly,
                 help_popup: None,
                 statistics: None,
             };
+            state.days_changed();
             tui_loop(state)
         }
-        Some(Action::Stat) => {
-            use wd::stat::{total_stats, weekly_stats};
+        Some(Action::Stat {
+            tag,
+            by_project,
+            format,
+            period,
+            range,
+        }) => {
+            use wd::stat::{period_stats, total_stats};
 
-            let mut days = load_days(&args.file_path)?;
+            let mut days = load_collection(&file_path)?.days;
             days.sort_by_key(|day| day.date);
+            // the vacation account covers all days, also with a tag filter
+            let today = Local::now().date_naive();
+            let vacation = settings.vacation.as_ref().map(|entitlement| {
+                wd::vacation::vacation_year(&days, entitlement, today.year(), today)
+            });
+            // the range and the targets cover all days, the tag only limits the work
+            let tag = tag.as_deref().map(|tag| tag.trim_start_matches('#'));
             if days.len() == 0 {
                 eprintln!("Can not stat on empty records");
             }
+            let range = range
+                .range(&days, today)
+                .map_err(|err| eprintln!("{err}"))?;
 
-            let stat_total = total_stats(&days)
+            let schedule = settings.schedule();
+            let stat_total = total_stats(&days, &schedule, range, tag)
                 .ok_or(())
                 .map_err(|()| eprintln!("Could not compute total stats"))?;
-            let stat_weekly = weekly_stats(&days);
-            let employ_duration = days.last().unwrap().date - days.first().unwrap().date;
+            if let Some(range) = range {
+                days.retain(|day| range.contains(day.date));
+            }
+            if by_project {
+                if let Some(tag) = tag {
+                    days.retain(|day| day.tags.contains(tag));
+                }
+                print_project_stat(&days, &stat_total, &settings);
+                return Ok(());
+            }
+            let stat_periods = period_stats(&days, &schedule, period, range, tag);
+            match format {
+                OutputFormat::Json => {
+                    return print_stat_json(&stat_periods, period, range, &stat_total)
+                        .map_err(|err| eprintln!("{err}"))
+                }
+                OutputFormat::Csv => {
+                    return print_stat_csv(&stat_periods, period, range, &stat_total)
+                        .map_err(|err| eprintln!("{err}"))
+                }
+                OutputFormat::Text => (),
+            }
+            let employ_duration = match range {
+                Some(range) => chrono::Duration::days(range.num_days()),
+                None => {
+                    let mut tracked = days.iter().filter(|day| has_target(day));
+                    let first = tracked.next().unwrap().date;
+                    tracked.next_back().map_or(first, |day| day.date) - first
+                }
+            };
+
+            print_stat(
+                &stat_periods,
+                period,
+                &stat_total,
+                &employ_duration,
+                vacation.as_ref(),
+                &settings,
+            )
+        }
+        Some(Action::Start) => punch(&file_path, settings.backups, |days, date, now| {
+            wd::punch::start(days, date, now)
+        }),
+        Some(Action::Stop) => punch(&file_path, settings.backups, |days, date, now| {
+            wd::punch::stop(days, date, now)
+        }),
+        Some(Action::Break {
+            action: BreakAction::Start,
+        }) => punch(&file_path, settings.backups, |days, date, now| {
+            wd::punch::break_start(days, date, now)
+        }),
+        Some(Action::Break {
+            action: BreakAction::End,
+        }) => punch(&file_path, settings.backups, |days, date, now| {
+            wd::punch::break_end(days, date, now)
+        }),
+        Some(Action::Holidays { year, state }) => {
+            let mut collection = load_collection(&file_path)?;
+            let added =
+                wd::holidays::prefill(&mut collection.days, &settings.schedule(), year, state);
+            save_collection(&file_path, &collection, settings.backups)?;
+            for (date, name) in &added {
+                println!("{} {name}", date.format(&settings.date_format));
+            }
+            println!("Added {} holidays to {}", added.len(), file_path);
+            Ok(())
+        }
+        Some(Action::Config) => {
+            let collection_config = collection_config_path(&file_path);
+            for (name, path) in [
+                ("global config", global_config_path()),
+                ("collection config", Some(collection_config)),
+            ] {
+                match path {
+                    Some(path) if path.exists() => println!("# {name}: {}", path.display()),
+                    Some(path) => println!("# {name}: {} (not found)", path.display()),
+                    None => println!("# {name}: none"),
+                }
+            }
+            print!("{}", settings.to_toml());
+            Ok(())
+        }
+        Some(Action::Check) => {
+            let days = load_collection(&file_path)?.days;
+            let violations = wd::check::check(&days, &settings.rules);
+            for (index, violation) in &violations {
+                println!(
+                    "{}: {violation}",
+                    days[*index].date.format(&settings.date_format)
+                );
+            }
+            if violations.is_empty() {
+                println!("No violations in {} entries", days.len());
+                Ok(())
+            } else {
+                eprintln!("Found {} violations", violations.len());
+                std::process::exit(1);
+            }
+        }
+        Some(Action::Balance {
+            action:
+                Some(BalanceAction::Book {
+                    kind,
+                    amount,
+                    date,
+                    note,
+                }),
+        }) => {
+            let mut collection = load_collection(&file_path)?;
+            let date = match (date, kind) {
+                (Some(date), _) => date,
+                // the opening balance is the balance when the collection starts, prefilled
+                // holidays do not count as its start
+                (None, BookingKind::Opening) => collection
+                    .days
+                    .iter()
+                    .filter(|day| has_target(day))
+                    .map(|day| day.date)
+                    .min()
+                    .ok_or(())
+                    .map_err(|()| {
+                        eprintln!(
+                            "{file_path} has no entries yet, give the date of the opening \
+                             balance with --date"
+                        )
+                    })?,
+                (None, _) => Local::now().date_naive(),
+            };
+            let booking = Booking {
+                date,
+                kind,
+                amount: parse_signed_duration(&amount).map_err(|err| eprintln!("{err}"))?,
+                note,
+            };
+            check_booking(&collection.bookings, &booking).map_err(|err| eprintln!("{err}"))?;
+            println!(
+                "Booked {kind} of {amount} on {date}",
+                amount = wd::disp_utils::signed_hm_from_duration(booking.amount),
+                date = booking.date.format(&settings.date_format)
+            );
+            collection.bookings.push(booking);
+            save_collection(&file_path, &collection, settings.backups)
+        }
+        Some(Action::Balance { action: None }) => {
+            use wd::stat::weekly_stats;
 
-            print_stat(&stat_weekly, &stat_total, &employ_duration)
+            let Collection { days, mut bookings } = load_collection(&file_path)?;
+            bookings.sort_by_key(|booking| booking.date);
+            let mut weeks = weekly_stats(&days, &settings.schedule(), None);
+            let balance = running_balance(&mut weeks, Period::Week, &bookings);
+            print_balance(&weeks, &bookings, balance, &settings);
+            Ok(())
+        }
+        Some(Action::Export {
+            format,
+            output,
+            mut types,
+            owner,
+        }) => {
+            let mut days = load_collection(&file_path)?.days;
+            days.sort_by_key(|day| day.date);
+            if types.is_empty() {
+                types = format.default_types();
+            }
+            days.retain(|day| types.contains(&TypeName::of(&day.day_type)));
+            let uid_base = owner.clone().unwrap_or_else(|| {
+                let path = std::path::Path::new(&file_path);
+                path.file_stem().map_or(file_path.clone(), |stem| {
+                    stem.to_string_lossy().into_owned()
+                })
+            });
+            let write = |writer: &mut dyn std::io::Write| match format {
+                ExportFormat::Csv => write_csv(&days, writer),
+                ExportFormat::Ics => write_ics(&days, &uid_base, owner.as_deref(), writer),
+            };
+            match output {
+                Some(path) => {
+                    let mut file = std::fs::File::create(&path).map_err(|err| {
+                        eprintln!("Could not create file {path}: {err}");
+                    })?;
+                    write(&mut file).map_err(|err| eprintln!("{err}"))?;
+                    println!("Exported {} entries to {path}", days.len());
+                    Ok(())
+                }
+                None => write(&mut stdout()).map_err(|err| eprintln!("{err}")),
+            }
+        }
+        Some(Action::Restore { backup: None }) => {
+            let backups = wd::backup::list(&file_path).map_err(|err| eprintln!("{err}"))?;
+            if backups.is_empty() {
+                println!(
+                    "No backups of {file_path} in {}",
+                    wd::backup::backup_dir(&file_path).display()
+                );
+                return Ok(());
+            }
+            let time_format = format!("{} %H:%M:%S", settings.date_format);
+            for (i, backup) in backups.iter().enumerate() {
+                let contents = match wd::storage::open(&backup.path.to_string_lossy()).load() {
+                    Ok(collection) => format!(
+                        "{} entries, {} bookings",
+                        collection.days.len(),
+                        collection.bookings.len()
+                    ),
+                    Err(err) => err,
+                };
+                println!(
+                    "{:>3}  {}  {contents}",
+                    i + 1,
+                    backup.created.format(&time_format)
+                );
+            }
+            println!("Roll back to one of them with `wd {file_path} restore <number>`");
+            Ok(())
+        }
+        Some(Action::Restore {
+            backup: Some(number),
+        }) => {
+            let backups = wd::backup::list(&file_path).map_err(|err| eprintln!("{err}"))?;
+            let backup = number
+                .checked_sub(1)
+                .and_then(|i| backups.get(i))
+                .ok_or_else(|| {
+                    eprintln!(
+                        "There is no backup {number}, {file_path} has {} backups",
+                        backups.len()
+                    )
+                })?;
+            wd::backup::restore(&file_path, backup).map_err(|err| eprintln!("{err}"))?;
+            println!(
+                "Restored {file_path} from {}, the state before is the newest backup now",
+                backup
+                    .created
+                    .format(&format!("{} %H:%M:%S", settings.date_format))
+            );
+            Ok(())
+        }
+        Some(Action::Upgrade) => {
+            let mut storage = wd::storage::open(&file_path);
+            match upgrade_collection(&mut *storage, &file_path)? {
+                Some(note) => println!("{note}"),
+                None => println!("{file_path} is up to date"),
+            }
+            Ok(())
+        }
+        Some(Action::Migrate { to, output }) => {
+            let output = output.unwrap_or_else(|| {
+                let path = std::path::Path::new(&file_path).with_extension(to.extension());
+                path.to_string_lossy().into_owned()
+            });
+            if Backend::of(&output) != to {
+                eprintln!("{output} does not have an extension of {to} collections");
+                return Err(());
+            }
+            if std::path::Path::new(&output).exists() {
+                eprintln!("{output} already exists, choose another file with --output");
+                return Err(());
+            }
+            let collection = load_collection(&file_path)?;
+            save_collection(&output, &collection, 0)?;
+            println!(
+                "Migrated {} entries and {} bookings from {file_path} to {output}",
+                collection.days.len(),
+                collection.bookings.len()
+            );
+            Ok(())
+        }
+        Some(Action::Import { csv_file }) => {
+            let mut collection = load_collection(&file_path)?;
+            let file = std::fs::File::open(&csv_file).map_err(|err| {
+                eprintln!("Could not read file {csv_file}: {err}");
+            })?;
+            let imported = read_csv(file, &collection.days).map_err(|errors| {
+                for err in &errors {
+                    eprintln!("{err}");
+                }
+                eprintln!("Imported nothing from {csv_file}");
+            })?;
+            println!(
+                "Imported {} entries from {csv_file} into {file_path}",
+                imported.len()
+            );
+            collection.days.extend(imported);
+            collection.days.sort_by_key(|day| day.date);
+            save_collection(&file_path, &collection, settings.backups)
         }
     }
 }
@@ -0,0 +1,160 @@
+use chrono::{NaiveDate, NaiveTime};
+
+use crate::work_day::{DayType, Interval, WorkDay};
+
+/// Finds the work day with a running session. This is either the work day `date` or, for
+/// overnight shifts, the work day before.
+fn running_mut(days: &mut [WorkDay], date: NaiveDate) -> Option<&mut WorkDay> {
+    days.iter_mut()
+        .rev()
+        .find(|day| (day.date == date || Some(day.date) == date.pred_opt()) && day.is_running())
+}
+
+/// Starts a work session in the work day `date` at `now`, creating a new `Present` entry if there
+/// is none yet
+pub fn start(days: &mut Vec<WorkDay>, date: NaiveDate, now: NaiveTime) -> Result<String, String> {
+    if let Some(running) = running_mut(days, date) {
+        return Err(format!(
+            "Work day {} is already running since {}",
+            running.date,
+            running.start().unwrap().format("%H:%M")
+        ));
+    }
+
+    match days
+        .iter_mut()
+        .rev()
+        .find(|day| day.date == date)
+        .map(|day| &mut day.day_type)
+    {
+        None => {
+            days.push(WorkDay::new(
+                date,
+                DayType::Present {
+                    sessions: vec![Interval {
+                        start: now,
+                        end: None,
+                        next_day: false,
+                        start_next_day: false,
+                    }],
+                    breaks: Vec::new(),
+                },
+            ));
+            days.sort_by_key(|day| day.date);
+            Ok(format!(
+                "Started work day {date} at {}",
+                now.format("%H:%M")
+            ))
+        }
+        Some(DayType::Present { sessions, .. }) | Some(DayType::HomeOffice { sessions, .. }) => {
+            if sessions.last().is_some_and(|session| session.next_day) {
+                return Err(format!("Work day {date} already ended on the next day"));
+            }
+            sessions.push(Interval {
+                start: now,
+                end: None,
+                next_day: false,
+                start_next_day: false,
+            });
+            Ok(format!(
+                "Started new session of work day {date} at {}",
+                now.format("%H:%M")
+            ))
+        }
+        Some(_) => Err(format!("Entry of {date} can not be started")),
+    }
+}
+
+/// The running `interval` ended at `now`, `next_day` if `now` is on the day after the work day.
+/// Fails if it would end before it starts or last longer than a day.
+fn ended(
+    interval: &Interval,
+    now: NaiveTime,
+    next_day: bool,
+    name: &str,
+) -> Result<Interval, String> {
+    let ended = Interval {
+        end: Some(now),
+        next_day,
+        ..*interval
+    };
+    ended
+        .validate(name)
+        .map_err(|err| format!("{err}, fix the entry in the app"))?;
+    Ok(ended)
+}
+
+/// Stops the running work session at `now` on `date`, ending a running break as well
+pub fn stop(days: &mut [WorkDay], date: NaiveDate, now: NaiveTime) -> Result<String, String> {
+    let day = running_mut(days, date).ok_or(format!("No running work day on {date}"))?;
+    let next_day = day.date != date;
+    match &mut day.day_type {
+        DayType::Present { sessions, breaks } | DayType::HomeOffice { sessions, breaks } => {
+            let running = sessions
+                .iter_mut()
+                .find(|session| session.end.is_none())
+                .unwrap();
+            let running_break = breaks.iter_mut().find(|brk| brk.end.is_none());
+            // nothing is changed if either of them can not be ended
+            let ended_session = ended(running, now, next_day, "The running work session")?;
+            if let Some(brk) = running_break {
+                *brk = ended(brk, now, next_day, "The running break")?;
+            }
+            *running = ended_session;
+            Ok(format!(
+                "Stopped work day {} at {}{}",
+                day.date,
+                now.format("%H:%M"),
+                if next_day { " on the next day" } else { "" }
+            ))
+        }
+        _ => Err(format!("No running work day on {date}")),
+    }
+}
+
+/// Starts a break in the running work day at `now` on `date`
+pub fn break_start(
+    days: &mut [WorkDay],
+    date: NaiveDate,
+    now: NaiveTime,
+) -> Result<String, String> {
+    let day = running_mut(days, date).ok_or(format!("No running work day on {date}"))?;
+    match &mut day.day_type {
+        DayType::Present { breaks, .. } | DayType::HomeOffice { breaks, .. } => {
+            match breaks.iter().find(|brk| brk.end.is_none()) {
+                None => {
+                    breaks.push(Interval {
+                        start: now,
+                        end: None,
+                        next_day: false,
+                        start_next_day: day.date != date,
+                    });
+                    Ok(format!("Started break at {}", now.format("%H:%M")))
+                }
+                Some(Interval { start, .. }) => Err(format!(
+                    "Break is already running since {}",
+                    start.format("%H:%M")
+                )),
+            }
+        }
+        _ => Err(format!("No running work day on {date}")),
+    }
+}
+
+/// Ends the running break in the running work day at `now` on `date`
+pub fn break_end(days: &mut [WorkDay], date: NaiveDate, now: NaiveTime) -> Result<String, String> {
+    let day = running_mut(days, date).ok_or(format!("No running break on {date}"))?;
+    let next_day = day.date != date;
+    match &mut day.day_type {
+        DayType::Present { breaks, .. } | DayType::HomeOffice { breaks, .. } => {
+            match breaks.iter_mut().find(|brk| brk.end.is_none()) {
+                Some(brk) => {
+                    *brk = ended(brk, now, next_day, "The running break")?;
+                    Ok(format!("Ended break at {}", now.format("%H:%M")))
+                }
+                None => Err(format!("No running break on {date}")),
+            }
+        }
+        _ => Err(format!("No running break on {date}")),
+    }
+}
@@ -0,0 +1,190 @@
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::work_day::{DayType, WorkDay};
+
+/// Target hours for each day of the week, days without hours are no work days
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DayHours {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mon: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tue: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wed: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thu: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fri: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sat: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sun: Option<f32>,
+}
+
+impl DayHours {
+    pub fn get(&self, weekday: Weekday) -> Option<f32> {
+        match weekday {
+            Weekday::Mon => self.mon,
+            Weekday::Tue => self.tue,
+            Weekday::Wed => self.wed,
+            Weekday::Thu => self.thu,
+            Weekday::Fri => self.fri,
+            Weekday::Sat => self.sat,
+            Weekday::Sun => self.sun,
+        }
+    }
+
+    fn all(&self) -> [Option<f32>; 7] {
+        [
+            self.mon, self.tue, self.wed, self.thu, self.fri, self.sat, self.sun,
+        ]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.all().iter().all(Option::is_none)
+    }
+}
+
+/// Working hours of a contract period, in effect from `from` until the next period starts
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Contract {
+    /// First day of the period, `None` for the period before all others
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<NaiveDate>,
+    /// Weekly hours, spread evenly over the work days
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub week_hours: Option<f32>,
+    #[serde(default = "default_work_days")]
+    pub work_days: Vec<Weekday>,
+    /// Hours per weekday, replaces `week_hours` and `work_days` if given
+    #[serde(default)]
+    #[serde(skip_serializing_if = "DayHours::is_empty")]
+    pub day_hours: DayHours,
+}
+
+pub fn default_work_days() -> Vec<Weekday> {
+    vec![
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+    ]
+}
+
+impl Contract {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.day_hours.is_empty() {
+            return match self
+                .day_hours
+                .all()
+                .into_iter()
+                .flatten()
+                .find(|hours| !(0.0..=24.0).contains(hours))
+            {
+                Some(hours) => Err(format!("invalid day_hours {hours}")),
+                None => Ok(()),
+            };
+        }
+        match self.week_hours {
+            None => Err(String::from("a contract needs week_hours or day_hours")),
+            Some(week_hours) if !(0.0..=168.0).contains(&week_hours) => {
+                Err(format!("invalid week_hours {week_hours}"))
+            }
+            Some(_) if self.work_days.is_empty() => {
+                Err(String::from("a contract needs at least one work day"))
+            }
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Target of a single day with weekday `weekday`, zero if it is no work day
+    pub fn daily_target(&self, weekday: Weekday) -> Duration {
+        if !self.day_hours.is_empty() {
+            hours(self.day_hours.get(weekday).unwrap_or(0.0))
+        } else if self.work_days.contains(&weekday) {
+            hours(self.week_hours.unwrap_or(0.0) / self.work_days.len() as f32)
+        } else {
+            Duration::zero()
+        }
+    }
+}
+
+/// The contract periods of a collection, sorted by their start
+pub struct Schedule {
+    contracts: Vec<Contract>,
+}
+
+impl Schedule {
+    /// Schedule with `initial` in effect until the first of `contracts` starts
+    pub fn new(initial: Contract, contracts: &[Contract]) -> Self {
+        let mut ret = vec![Contract {
+            from: None,
+            ..initial
+        }];
+        ret.extend_from_slice(contracts);
+        ret.sort_by_key(|contract| contract.from);
+        Self { contracts: ret }
+    }
+
+    /// Contract in effect on `date`
+    pub fn contract(&self, date: NaiveDate) -> &Contract {
+        self.contracts
+            .iter()
+            .rev()
+            .find(|contract| contract.from.is_none_or(|from| from <= date))
+            .unwrap()
+    }
+
+    pub fn daily_target(&self, date: NaiveDate) -> Duration {
+        self.contract(date).daily_target(date.weekday())
+    }
+
+    /// Target of the week starting at `week_start`, lowered by the target of every holiday in
+    /// `days`
+    pub fn week_target(&self, week_start: NaiveDate, days: &[WorkDay]) -> Duration {
+        self.target(week_start, week_start + Duration::days(6), days)
+    }
+
+    /// Target of the days from `first` to `last`, lowered by the target of every holiday in
+    /// `days`
+    pub fn target(&self, first: NaiveDate, last: NaiveDate, days: &[WorkDay]) -> Duration {
+        first
+            .iter_days()
+            .take_while(|date| *date <= last)
+            .filter(|date| {
+                !days
+                    .iter()
+                    .any(|day| day.date == *date && matches!(day.day_type, DayType::Holiday { .. }))
+            })
+            .fold(Duration::zero(), |acc, date| acc + self.daily_target(date))
+    }
+
+    /// Difference between the worked time and the daily target of a finished `day`, vacation and
+    /// sick leave are credited and holidays have no target
+    pub fn day_surplus(&self, day: &WorkDay) -> Duration {
+        let target = match day.day_type {
+            DayType::Holiday { .. } => Duration::zero(),
+            _ => self.daily_target(day.date),
+        };
+        day.worked_time() + self.credit(day) - target
+    }
+
+    /// Share of the daily target credited for vacation and sick leave on `day`
+    pub fn credit(&self, day: &WorkDay) -> Duration {
+        let target = self.daily_target(day.date);
+        Duration::seconds(
+            (target.num_seconds() as f32 * (day.vacation_share() + day.sick_share())) as i64,
+        )
+    }
+}
+
+/// Duration of a fractional number of `hours`
+pub fn hours(hours: f32) -> Duration {
+    Duration::milliseconds((hours * 60.0 * 60.0 * 1000.0) as i64)
+}
@@ -1,65 +1,128 @@
+use crate::schedule::Schedule;
 use crate::work_day::{DayType, WorkDay};
-use chrono::{Duration, NaiveDate, NaiveTime};
-use std::collections::HashMap;
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime};
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
+/// Statistics of a number of days. Serialized with all durations in minutes.
+#[derive(Serialize)]
 pub struct StatUnit {
+    #[serde(rename = "work_minutes", serialize_with = "as_minutes")]
     pub work: Duration,
     pub active_days: u32,
-    pub sick_days: u32,
+    /// Sick days, partial sick leave counts with its share of the day
+    pub sick_days: f32,
+    /// Vacation days, partial vacation counts with its share of the day
+    pub vacation_days: f32,
+    /// Days off to reduce overtime, partial compensation counts with its share of the day
+    pub compensation_days: f32,
+    /// Public holidays on work days, holidays on weekends do not lower the target
+    pub holidays: u32,
     pub home_office_days: u32,
     pub num_days: u32,
 
+    #[serde(rename = "break_minutes", serialize_with = "as_minutes")]
     pub brk: Duration,
+    /// Worked time booked on each project
+    #[serde(rename = "project_minutes", serialize_with = "projects_as_minutes")]
+    pub projects: BTreeMap<String, Duration>,
+
+    /// Target of all weeks with entries, according to the contract in effect
+    #[serde(rename = "target_minutes", serialize_with = "as_minutes")]
+    pub target: Duration,
+    /// Share of the daily target credited for vacation and sick leave
+    #[serde(rename = "credited_minutes", serialize_with = "as_minutes")]
+    pub credited: Duration,
+    /// Flex balance at the end of the unit, see [`crate::balance::running_balance`]. Not
+    /// serialized, as it is only known with the bookings of the whole collection.
+    #[serde(skip)]
+    pub balance: Duration,
 
     pub mean_start: Option<NaiveTime>,
     pub mean_end: Option<NaiveTime>,
+    /// Mean end as an offset from the start of the day, see [`WorkDay::end_offset`]
+    #[serde(skip)]
+    pub mean_end_offset: Option<Duration>,
 }
 
 impl StatUnit {
     pub fn from_single_day(day: &WorkDay) -> Self {
         match &day.day_type {
-            DayType::Present { start, end, .. } | DayType::Unofficial { start, end, .. } => Self {
+            DayType::Present { .. } | DayType::Unofficial { .. } => Self {
                 work: day.worked_time(),
                 active_days: 1,
-                sick_days: 0,
+                sick_days: day.sick_share(),
+                vacation_days: day.vacation_share(),
+                compensation_days: day.compensation_share(),
+                holidays: 0,
                 home_office_days: 0,
                 num_days: 1,
                 brk: day.break_time(),
-                mean_start: Some(*start),
-                mean_end: Some(*end),
+                projects: day.project_times(),
+                target: Duration::zero(),
+                credited: Duration::zero(),
+                balance: Duration::zero(),
+                mean_start: day.start(),
+                mean_end: day.end(),
+                mean_end_offset: day.end_offset(),
             },
-            DayType::HomeOffice { start, end, .. } => Self {
+            DayType::HomeOffice { .. } => Self {
                 work: day.worked_time(),
                 active_days: 1,
-                sick_days: 0,
+                sick_days: day.sick_share(),
+                vacation_days: day.vacation_share(),
+                compensation_days: day.compensation_share(),
+                holidays: 0,
                 num_days: 1,
                 brk: day.break_time(),
+                projects: day.project_times(),
+                target: Duration::zero(),
+                credited: Duration::zero(),
+                balance: Duration::zero(),
                 home_office_days: 1,
-                mean_start: Some(*start),
-                mean_end: Some(*end),
+                mean_start: day.start(),
+                mean_end: day.end(),
+                mean_end_offset: day.end_offset(),
             },
             DayType::Sick => {
                 let mut ret = Self::default();
-                ret.sick_days = 1;
+                ret.sick_days = 1.0;
                 ret.num_days = 1;
                 return ret;
             }
-            DayType::Travel { start, end } => Self {
+            DayType::Travel { .. } => Self {
                 work: day.worked_time(),
                 active_days: 1,
-                sick_days: 0,
+                sick_days: day.sick_share(),
+                vacation_days: day.vacation_share(),
+                compensation_days: day.compensation_share(),
+                holidays: 0,
                 num_days: 1,
                 brk: day.break_time(),
+                projects: day.project_times(),
+                target: Duration::zero(),
+                credited: Duration::zero(),
+                balance: Duration::zero(),
                 home_office_days: 1,
-                mean_start: Some(*start),
-                mean_end: Some(*end),
+                mean_start: day.start(),
+                mean_end: day.end(),
+                mean_end_offset: day.end_offset(),
             },
             DayType::Vacation => {
                 let mut ret = Self::default();
-                ret.sick_days = 0;
+                ret.vacation_days = 1.0;
                 ret.num_days = 1;
                 return ret;
             }
+            DayType::Compensation => Self {
+                compensation_days: 1.0,
+                num_days: 1,
+                ..Self::default()
+            },
+            DayType::Holiday { .. } => Self {
+                num_days: 1,
+                ..Self::default()
+            },
         }
     }
 
@@ -77,72 +140,305 @@ impl StatUnit {
         self.mean_start = Some(NaiveTime::from_hms_opt(hours as u32, minutes as u32, 0).unwrap())
     }
 
-    pub fn update_mean_end(&mut self, end: &NaiveTime) {
-        let me_since_mn =
-            self.mean_end.map_or(Duration::zero(), dur_since_mn) * self.active_days as i32;
-        let ce_since_mn = dur_since_mn(*end);
-
+    /// Updates the mean end with the end of a day as an offset from its start, so ends on the
+    /// next day count as later than all ends on the same day
+    pub fn update_mean_end(&mut self, end_offset: Duration) {
+        let me_offset = self.mean_end_offset.unwrap_or(Duration::zero()) * self.active_days as i32;
         let new_avg_duration = Duration::minutes(
-            (me_since_mn + ce_since_mn).num_minutes() / (self.active_days + 1) as i64,
+            (me_offset + end_offset).num_minutes() / (self.active_days + 1) as i64,
         );
-        let hours = new_avg_duration.num_hours();
-        let minutes = new_avg_duration.num_minutes() - hours * 60;
 
-        self.mean_end = Some(NaiveTime::from_hms_opt(hours as u32, minutes as u32, 0).unwrap());
+        self.mean_end_offset = Some(new_avg_duration);
+        // the mean end wraps around to the next day
+        self.mean_end = Some(NaiveTime::MIN + new_avg_duration);
     }
 
-    pub fn push_day(&mut self, day: &WorkDay) {
+    /// Updates mean start and end with the first start and last end of `day`
+    fn update_mean_times(&mut self, day: &WorkDay) {
+        if let Some(start) = day.start() {
+            self.update_mean_start(&start);
+        }
+        if let Some(end_offset) = day.end_offset() {
+            self.update_mean_end(end_offset);
+        }
+    }
+
+    /// Difference between the work time and the target. Vacation and sick leave are credited
+    /// with their share of the daily target. Compensation days are not credited, so they reduce
+    /// the surplus by the daily target.
+    pub fn surplus(&self) -> Duration {
+        self.work + self.credited - self.target
+    }
+
+    /// Statistics of `day`, only with its absences if it does not have `tag`, see [`has_tag`]
+    fn of_day(day: &WorkDay, tag: Option<&str>) -> Self {
+        if has_tag(day, tag) {
+            Self::from_single_day(day)
+        } else {
+            let mut ret = Self::default();
+            ret.push_absences(day);
+            ret
+        }
+    }
+
+    /// Adds `day`, only with its absences if it does not have `tag`, see [`has_tag`]
+    fn push_tagged(&mut self, day: &WorkDay, tag: Option<&str>) {
+        if has_tag(day, tag) {
+            self.push_day(day);
+        } else {
+            self.push_absences(day);
+        }
+    }
+
+    /// Adds `day` without its work
+    fn push_absences(&mut self, day: &WorkDay) {
         self.num_days += 1;
+        self.sick_days += day.sick_share();
+        self.vacation_days += day.vacation_share();
+        self.compensation_days += day.compensation_share();
+    }
+
+    pub fn push_day(&mut self, day: &WorkDay) {
+        self.push_absences(day);
+        for (project, time) in day.project_times() {
+            let total = self.projects.entry(project).or_insert(Duration::zero());
+            *total = *total + time;
+        }
         match &day.day_type {
-            DayType::Present { start, end, .. } | DayType::Unofficial { start, end, .. } => {
-                self.update_mean_start(start);
-                self.update_mean_end(end);
+            DayType::Present { .. } | DayType::Unofficial { .. } => {
+                self.update_mean_times(day);
                 self.brk = self.brk + day.break_time();
                 self.work = self.work + day.worked_time();
 
                 self.active_days += 1;
             }
-            DayType::HomeOffice { start, end, .. } => {
-                self.update_mean_start(start);
-                self.update_mean_end(end);
+            DayType::HomeOffice { .. } => {
+                self.update_mean_times(day);
                 self.brk = self.brk + day.break_time();
                 self.work = self.work + day.worked_time();
                 self.home_office_days += 1;
 
                 self.active_days += 1;
             }
-            DayType::Sick => self.sick_days += 1,
-            DayType::Travel { start, end } => {
-                self.update_mean_start(start);
-                self.update_mean_end(end);
-            }
-            DayType::Vacation => (),
+            DayType::Travel { .. } => self.update_mean_times(day),
+            DayType::Holiday { .. } => (),
+            DayType::Sick | DayType::Vacation | DayType::Compensation => (),
+        }
+    }
+}
+
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date.week(chrono::Weekday::Mon).first_day()
+}
+
+/// Length of the periods statistics are grouped by
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Period {
+    Week,
+    Month,
+    Year,
+}
+
+impl Period {
+    const NAMES: [(&'static str, Period); 3] = [
+        ("week", Period::Week),
+        ("month", Period::Month),
+        ("year", Period::Year),
+    ];
+
+    /// First day of the period containing `date`
+    pub fn start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Week => week_start(date),
+            Period::Month => date.with_day(1).unwrap(),
+            Period::Year => date.with_ordinal(1).unwrap(),
+        }
+    }
+
+    /// Last day of the period starting at `start`
+    pub fn end(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Week => start + Duration::days(6),
+            Period::Month => start + chrono::Months::new(1) - Duration::days(1),
+            Period::Year => start + chrono::Months::new(12) - Duration::days(1),
+        }
+    }
+
+    /// Next longer period, wrapping around to weeks
+    pub fn next(&self) -> Self {
+        match self {
+            Period::Week => Period::Month,
+            Period::Month => Period::Year,
+            Period::Year => Period::Week,
         }
     }
 }
 
-pub fn total_stats(days: &[WorkDay]) -> Option<StatUnit> {
-    let mut total = StatUnit::from_single_day(days.first()?);
+impl std::str::FromStr for Period {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Period::NAMES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+            .map(|(_, period)| *period)
+            .ok_or(format!("unknown period {s}, expected week, month or year"))
+    }
+}
 
-    for day in days.iter().skip(1) {
-        total.push_day(day);
+impl std::fmt::Display for Period {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (name, _) = Period::NAMES
+            .iter()
+            .find(|(_, period)| period == self)
+            .unwrap();
+        write!(f, "{name}")
     }
+}
 
+/// Days from `first` to `last`, both included
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct DateRange {
+    pub first: NaiveDate,
+    pub last: NaiveDate,
+}
+
+impl DateRange {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.first <= date && date <= self.last
+    }
+
+    /// Number of days in the range
+    pub fn num_days(&self) -> i64 {
+        (self.last - self.first).num_days() + 1
+    }
+
+    /// Whole weeks from the first to the last finished entry of `days` in `range`, limited to
+    /// `range`. All entries count if it is `None`, holidays do not count, see [`has_target`].
+    fn of_entries(days: &[WorkDay], range: Option<DateRange>) -> Option<Self> {
+        let mut finished = days.iter().filter(|day| {
+            !day.is_running()
+                && has_target(day)
+                && range.is_none_or(|range| range.contains(day.date))
+        });
+        let first = finished.next()?.date;
+        let (first, last) = finished.fold((first, first), |(first, last), day| {
+            (first.min(day.date), last.max(day.date))
+        });
+        let entries = Self {
+            first: week_start(first),
+            last: Period::Week.end(week_start(last)),
+        };
+        Some(match range {
+            Some(range) => Self {
+                first: entries.first.max(range.first),
+                last: entries.last.min(range.last),
+            },
+            None => entries,
+        })
+    }
+
+    /// Target of the part of the period starting at `start` that lies in the range
+    fn target(
+        &self,
+        schedule: &Schedule,
+        period: Period,
+        start: NaiveDate,
+        days: &[WorkDay],
+    ) -> Duration {
+        schedule.target(
+            start.max(self.first),
+            period.end(start).min(self.last),
+            days,
+        )
+    }
+}
+
+/// Days that are still running are not included in the statistics. Only days in `range` are
+/// included, all days if it is `None`. The target covers all weeks with entries, as far as they
+/// lie in the range. Holidays are only included in weeks with other entries, see [`has_target`].
+/// With a `tag`, only the work of the days with the tag is included.
+pub fn total_stats(
+    days: &[WorkDay],
+    schedule: &Schedule,
+    range: Option<DateRange>,
+    tag: Option<&str>,
+) -> Option<StatUnit> {
+    let range = DateRange::of_entries(days, range)?;
+    let finished = days
+        .iter()
+        .filter(|day| !day.is_running() && range.contains(day.date));
+    let weeks = finished
+        .clone()
+        .filter(|day| has_target(day))
+        .map(|day| week_start(day.date))
+        .collect::<BTreeSet<_>>();
+    let mut counted = finished.filter(|day| weeks.contains(&week_start(day.date)));
+    let first = counted.next()?;
+    let mut total = StatUnit::of_day(first, tag);
+    total.credited = schedule.credit(first);
+    total.holidays = is_holiday_on_work_day(first, schedule) as u32;
+
+    for day in counted {
+        total.push_tagged(day, tag);
+        total.credited = total.credited + schedule.credit(day);
+        total.holidays += is_holiday_on_work_day(day, schedule) as u32;
+    }
+
+    total.target = weeks.into_iter().fold(Duration::zero(), |acc, week| {
+        acc + range.target(schedule, Period::Week, week, days)
+    });
     Some(total)
 }
 
-pub fn weekly_stats(days: &[WorkDay]) -> Vec<(NaiveDate, StatUnit)> {
-    let mut stat_weeks: HashMap<NaiveDate, StatUnit> = HashMap::new();
+pub fn weekly_stats(
+    days: &[WorkDay],
+    schedule: &Schedule,
+    range: Option<DateRange>,
+) -> Vec<(NaiveDate, StatUnit)> {
+    period_stats(days, schedule, Period::Week, range, None)
+}
+
+/// Statistics of every period with entries in `range`, or of all days if it is `None`, sorted by
+/// the start of the period. The target of a period only covers the weeks from the first to the
+/// last finished entry in the range, so months and years that are not over yet do not count the
+/// days to come. Periods with nothing but holidays are left out, see [`has_target`]. With a `tag`,
+/// only the work of the days with the tag is included.
+pub fn period_stats(
+    days: &[WorkDay],
+    schedule: &Schedule,
+    period: Period,
+    range: Option<DateRange>,
+    tag: Option<&str>,
+) -> Vec<(NaiveDate, StatUnit)> {
+    let Some(range) = DateRange::of_entries(days, range) else {
+        return Vec::new();
+    };
+    let finished = days
+        .iter()
+        .filter(|day| !day.is_running() && range.contains(day.date));
+    let starts = finished
+        .clone()
+        .filter(|day| has_target(day))
+        .map(|day| period.start(day.date))
+        .collect::<BTreeSet<_>>();
+    let mut stat_periods: HashMap<NaiveDate, StatUnit> = HashMap::new();
+
+    for day in finished.filter(|day| starts.contains(&period.start(day.date))) {
+        let stat = stat_periods
+            .entry(period.start(day.date))
+            .and_modify(|entry| entry.push_tagged(day, tag))
+            .or_insert_with(|| StatUnit::of_day(day, tag));
+        stat.credited = stat.credited + schedule.credit(day);
+        stat.holidays += is_holiday_on_work_day(day, schedule) as u32;
+    }
 
-    for day in days.iter() {
-        stat_weeks
-            .entry(day.date.week(chrono::Weekday::Mon).first_day())
-            .and_modify(|entry| entry.push_day(day))
-            .or_insert(StatUnit::from_single_day(day));
+    let mut periods = stat_periods.drain().collect::<Vec<_>>();
+    for (start, stat) in periods.iter_mut() {
+        stat.target = range.target(schedule, period, *start, days);
     }
-    let mut weeks = stat_weeks.drain().collect::<Vec<_>>();
-    weeks.sort_by_key(|(week_start, _)| *week_start);
-    weeks
+    periods.sort_by_key(|(start, _)| *start);
+    periods
 }
 
 impl Default for StatUnit {
@@ -150,16 +446,158 @@ impl Default for StatUnit {
         Self {
             work: Duration::zero(),
             active_days: 0,
-            sick_days: 0,
+            sick_days: 0.0,
+            vacation_days: 0.0,
+            compensation_days: 0.0,
+            holidays: 0,
             num_days: 0,
             home_office_days: 0,
             brk: Duration::zero(),
+            projects: BTreeMap::new(),
+            target: Duration::zero(),
+            credited: Duration::zero(),
+            balance: Duration::zero(),
             mean_start: None,
             mean_end: None,
+            mean_end_offset: None,
         }
     }
 }
 
+/// Whether `day` makes its week or period count towards the target. Holidays only lower the target
+/// of weeks with other entries, so holidays added for a whole year do not add the target of the
+/// weeks before the first or after the last entry.
+pub fn has_target(day: &WorkDay) -> bool {
+    !matches!(day.day_type, DayType::Holiday { .. })
+}
+
+/// Whether the work of `day` counts towards statistics limited to `tag`. Days without the tag
+/// still count with their absences and lower or raise the target like all other days, so the
+/// surplus is the one of the tagged work.
+fn has_tag(day: &WorkDay, tag: Option<&str>) -> bool {
+    tag.is_none_or(|tag| day.tags.contains(tag))
+}
+
+/// Whether `day` is a holiday on a day with a target in `schedule`
+fn is_holiday_on_work_day(day: &WorkDay, schedule: &Schedule) -> bool {
+    matches!(day.day_type, DayType::Holiday { .. })
+        && schedule.daily_target(day.date) > Duration::zero()
+}
+
+fn as_minutes<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_i64(duration.num_minutes())
+}
+
+fn projects_as_minutes<S: Serializer>(
+    projects: &BTreeMap<String, Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(
+        projects
+            .iter()
+            .map(|(project, time)| (project, time.num_minutes())),
+    )
+}
+
 fn dur_since_mn(time: NaiveTime) -> Duration {
     time - NaiveTime::from_hms_opt(0, 0, 0).unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+    use crate::holidays::{prefill, State};
+    use crate::work_day::Interval;
+
+    fn work_day(date: NaiveDate) -> WorkDay {
+        WorkDay::new(
+            date,
+            DayType::Present {
+                sessions: vec![Interval {
+                    start: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+                    end: NaiveTime::from_hms_opt(16, 0, 0),
+                    next_day: false,
+                    start_next_day: false,
+                }],
+                breaks: Vec::new(),
+            },
+        )
+    }
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+    }
+
+    #[test]
+    fn holidays_do_not_add_target_weeks() {
+        let schedule = Settings::default().schedule();
+        let mut days = vec![work_day(date(10, 12))];
+        prefill(&mut days, &schedule, 2026, Some(State::Bayern));
+
+        let total = total_stats(&days, &schedule, None, None).unwrap();
+        assert_eq!(total.target, schedule.week_target(date(10, 12), &days));
+        assert_eq!(total.holidays, 0);
+        assert_eq!(total.num_days, 1);
+        for period in [Period::Week, Period::Month, Period::Year] {
+            let periods = period_stats(&days, &schedule, period, None, None);
+            assert_eq!(periods.len(), 1);
+            assert_eq!(periods[0].0, period.start(date(10, 12)));
+        }
+    }
+
+    #[test]
+    fn holidays_lower_target_of_weeks_with_entries() {
+        let schedule = Settings::default().schedule();
+        let mut days = vec![work_day(date(5, 11)), work_day(date(5, 27))];
+        prefill(&mut days, &schedule, 2026, Some(State::Bayern));
+
+        // christi himmelfahrt on thursday and pfingstmontag on monday, fronleichnam is in a
+        // week without entries
+        let total = total_stats(&days, &schedule, None, None).unwrap();
+        assert_eq!(total.holidays, 2);
+        assert_eq!(
+            total.target,
+            schedule.week_target(date(5, 11), &[]) * 2 - schedule.daily_target(date(5, 14)) * 2
+        );
+        let weeks = period_stats(&days, &schedule, Period::Week, None, None);
+        assert_eq!(
+            weeks.iter().map(|(start, _)| *start).collect::<Vec<_>>(),
+            vec![date(5, 11), date(5, 25)]
+        );
+        assert!(weeks.iter().all(|(_, week)| week.holidays == 1));
+    }
+
+    #[test]
+    fn tag_limits_only_the_work() {
+        let schedule = Settings::default().schedule();
+        let mut days = vec![
+            WorkDay {
+                tags: BTreeSet::from([String::from("release")]),
+                ..work_day(date(5, 11))
+            },
+            work_day(date(5, 12)),
+            WorkDay::new(date(5, 13), DayType::Vacation),
+            work_day(date(5, 18)),
+        ];
+        prefill(&mut days, &schedule, 2026, Some(State::Bayern));
+
+        let total = total_stats(&days, &schedule, None, Some("release")).unwrap();
+        let all = total_stats(&days, &schedule, None, None).unwrap();
+        assert_eq!(total.work, Duration::hours(8));
+        assert_eq!(total.active_days, 1);
+        assert_eq!((total.vacation_days, total.holidays), (1.0, 1));
+        assert_eq!((total.target, total.credited), (all.target, all.credited));
+        let weeks = period_stats(&days, &schedule, Period::Week, None, Some("release"));
+        assert_eq!(
+            weeks
+                .iter()
+                .map(|(start, week)| (*start, week.work))
+                .collect::<Vec<_>>(),
+            vec![
+                (date(5, 11), Duration::hours(8)),
+                (date(5, 18), Duration::zero())
+            ]
+        );
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::Path;
+
+use rusqlite::{Connection, OpenFlags, Transaction};
+
+use crate::balance::Booking;
+use crate::collection::{format_version, Collection, FORMAT_VERSION};
+use crate::work_day::WorkDay;
+
+/// Where a collection is kept
+pub trait Storage {
+    fn load(&mut self) -> Result<Collection, String>;
+    fn save(&mut self, days: &[WorkDay], bookings: &[Booking]) -> Result<(), String>;
+    /// Rewrites a collection of an older format in the current one after a backup, returns the
+    /// old and the new version if it was upgraded
+    fn upgrade(&mut self) -> Result<Option<(u32, u32)>, String>;
+    /// Whether the collection was changed by someone else since it was last loaded or saved here
+    fn changed(&self) -> Result<bool, String>;
+}
+
+/// Kinds of storage, chosen by the extension of the collection file
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Backend {
+    /// A single JSON document, see [`Collection`]
+    Json,
+    /// A SQLite database with a row per day and booking
+    Sqlite,
+}
+
+impl Backend {
+    /// `.db`, `.sqlite` and `.sqlite3` files are SQLite databases, all others JSON
+    pub fn of(file_path: &str) -> Self {
+        match Path::new(file_path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("db" | "sqlite" | "sqlite3") => Backend::Sqlite,
+            _ => Backend::Json,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Backend::Json => "json",
+            Backend::Sqlite => "db",
+        }
+    }
+}
+
+impl std::str::FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Backend::Json),
+            "sqlite" => Ok(Backend::Sqlite),
+            _ => Err(format!("unknown storage {s}, expected json or sqlite")),
+        }
+    }
+}
+
+impl std::fmt::Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Backend::Json => write!(f, "json"),
+            Backend::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+/// Storage of the collection at `file_path` with the backend of its extension
+pub fn open(file_path: &str) -> Box<dyn Storage> {
+    match Backend::of(file_path) {
+        Backend::Json => Box::new(JsonStorage {
+            file_path: String::from(file_path),
+            fingerprint: None,
+        }),
+        Backend::Sqlite => Box::new(SqliteStorage {
+            file_path: String::from(file_path),
+            fingerprint: None,
+        }),
+    }
+}
+
+/// Collection in a JSON file, it is replaced as a whole on every save
+pub struct JsonStorage {
+    file_path: String,
+    /// Fingerprint of the file as it was last loaded or saved
+    fingerprint: Option<u64>,
+}
+
+impl Storage for JsonStorage {
+    fn load(&mut self) -> Result<Collection, String> {
+        let data = std::fs::read_to_string(&self.file_path)
+            .map_err(|err| format!("Could not read file {}: {err}", self.file_path))?;
+        let collection = Collection::from_json(&data)
+            .map_err(|err| format!("Error during parsing of data: {err}"))?;
+        self.fingerprint = Some(hash(data.as_bytes()));
+        Ok(collection)
+    }
+
+    fn save(&mut self, days: &[WorkDay], bookings: &[Booking]) -> Result<(), String> {
+        let data = crate::collection::to_json(days, bookings)
+            .map_err(|err| format!("Could not serialize data: {err}"))?;
+        write_atomic(Path::new(&self.file_path), data.as_bytes())
+            .map_err(|err| format!("Could not write file {}: {err}", self.file_path))?;
+        self.fingerprint = Some(hash(data.as_bytes()));
+        Ok(())
+    }
+
+    fn upgrade(&mut self) -> Result<Option<(u32, u32)>, String> {
+        let data = std::fs::read_to_string(&self.file_path)
+            .map_err(|err| format!("Could not read file {}: {err}", self.file_path))?;
+        let version =
+            format_version(&data).map_err(|err| format!("Error during parsing of data: {err}"))?;
+        if version >= FORMAT_VERSION {
+            return Ok(None);
+        }
+        let collection = Collection::from_json(&data)
+            .map_err(|err| format!("Error during parsing of data: {err}"))?;
+        crate::backup::create(&self.file_path)?;
+        self.save(&collection.days, &collection.bookings)?;
+        Ok(Some((version, FORMAT_VERSION)))
+    }
+
+    fn changed(&self) -> Result<bool, String> {
+        Ok(fingerprint(&self.file_path)? != self.fingerprint)
+    }
+}
+
+fn hash(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hash of the contents of the file at `file_path`, `None` if it does not exist
+fn fingerprint(file_path: &str) -> Result<Option<u64>, String> {
+    match std::fs::read(file_path) {
+        Ok(data) => Ok(Some(hash(&data))),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(format!("Could not read file {file_path}: {err}")),
+    }
+}
+
+/// Writes `data` to a temporary file next to `path` and renames it to `path`, so `path` keeps its
+/// previous contents if writing fails
+pub fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    let write = || {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        std::fs::rename(&temp_path, path)
+    };
+    write().inspect_err(|_| {
+        let _ = std::fs::remove_file(&temp_path);
+    })
+}
+
+/// Version of the database layout, stored as `user_version`
+const SCHEMA_VERSION: i64 = 1;
+
+/// Collection in a SQLite database. Every day and booking is a row with its JSON representation,
+/// a save only inserts and deletes the rows that changed.
+pub struct SqliteStorage {
+    file_path: String,
+    /// Fingerprint of the database file after it was last loaded or saved
+    fingerprint: Option<u64>,
+}
+
+impl SqliteStorage {
+    fn connect(&self, flags: OpenFlags) -> Result<Connection, String> {
+        Connection::open_with_flags(&self.file_path, flags)
+            .map_err(|err| format!("Could not open database {}: {err}", self.file_path))
+    }
+
+    /// Version of the database, fails for databases written by a newer version of wd
+    fn check_version(transaction: &Transaction) -> Result<i64, String> {
+        let version: i64 = transaction
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(|err| err.to_string())?;
+        if version > SCHEMA_VERSION {
+            return Err(format!(
+                "database version {version} is newer than the supported version {SCHEMA_VERSION}"
+            ));
+        }
+        Ok(version)
+    }
+
+    /// Creates the tables of an empty database
+    fn create_tables(transaction: &Transaction) -> rusqlite::Result<()> {
+        transaction.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS days (
+                id INTEGER PRIMARY KEY,
+                date TEXT NOT NULL,
+                day TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS days_by_date ON days (date);
+            CREATE TABLE IF NOT EXISTS bookings (
+                id INTEGER PRIMARY KEY,
+                date TEXT NOT NULL,
+                booking TEXT NOT NULL
+            );
+            PRAGMA user_version = {SCHEMA_VERSION};"
+        ))
+    }
+
+    /// Contents of `column` of all rows of `table`, ordered by date
+    fn rows(transaction: &Transaction, table: &str, column: &str) -> rusqlite::Result<Vec<String>> {
+        let mut statement =
+            transaction.prepare(&format!("SELECT {column} FROM {table} ORDER BY date, id"))?;
+        let rows = statement.query_map([], |row| row.get(0))?.collect();
+        rows
+    }
+
+    /// Makes the rows of `table` equal to `rows` of date and contents, unchanged rows are kept
+    fn sync(
+        transaction: &Transaction,
+        table: &str,
+        column: &str,
+        rows: Vec<(String, String)>,
+    ) -> rusqlite::Result<()> {
+        let mut stored: HashMap<String, Vec<i64>> = HashMap::new();
+        let mut select = transaction.prepare(&format!("SELECT id, {column} FROM {table}"))?;
+        let mut query = select.query([])?;
+        while let Some(row) = query.next()? {
+            stored.entry(row.get(1)?).or_default().push(row.get(0)?);
+        }
+
+        let mut insert = transaction.prepare(&format!(
+            "INSERT INTO {table} (date, {column}) VALUES (?1, ?2)"
+        ))?;
+        for (date, contents) in rows {
+            if stored.get_mut(&contents).and_then(Vec::pop).is_none() {
+                insert.execute([date, contents])?;
+            }
+        }
+        let mut delete = transaction.prepare(&format!("DELETE FROM {table} WHERE id = ?1"))?;
+        for id in stored.into_values().flatten() {
+            delete.execute([id])?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the JSON representation of each row
+fn parse_rows<T: serde::de::DeserializeOwned>(rows: Vec<String>) -> Result<Vec<T>, String> {
+    rows.iter()
+        .map(|row| serde_json::from_str(row))
+        .collect::<Result<_, _>>()
+        .map_err(|err| format!("Error during parsing of data: {err}"))
+}
+
+/// Date and JSON representation of each row
+fn serialize_rows<T: serde::Serialize>(
+    rows: &[T],
+    date: fn(&T) -> chrono::NaiveDate,
+) -> Result<Vec<(String, String)>, String> {
+    rows.iter()
+        .map(|row| Ok((date(row).to_string(), serde_json::to_string(row)?)))
+        .collect::<Result<_, serde_json::Error>>()
+        .map_err(|err| format!("Could not serialize data: {err}"))
+}
+
+impl Storage for SqliteStorage {
+    fn load(&mut self) -> Result<Collection, String> {
+        let mut connection = self.connect(OpenFlags::SQLITE_OPEN_READ_WRITE)?;
+        let read = |connection: &mut Connection| {
+            let transaction = connection.transaction().map_err(|err| err.to_string())?;
+            // an up to date database is only read, so its file stays the same
+            if Self::check_version(&transaction)? < SCHEMA_VERSION {
+                Self::create_tables(&transaction).map_err(|err| err.to_string())?;
+            }
+            let days = Self::rows(&transaction, "days", "day").map_err(|err| err.to_string())?;
+            let bookings =
+                Self::rows(&transaction, "bookings", "booking").map_err(|err| err.to_string())?;
+            transaction.commit().map_err(|err| err.to_string())?;
+            Ok::<_, String>((days, bookings))
+        };
+        let (days, bookings) = read(&mut connection)
+            .map_err(|err| format!("Could not read database {}: {err}", self.file_path))?;
+        drop(connection);
+        self.fingerprint = fingerprint(&self.file_path)?;
+        Ok(Collection {
+            days: parse_rows(days)?,
+            bookings: parse_rows(bookings)?,
+        })
+    }
+
+    fn save(&mut self, days: &[WorkDay], bookings: &[Booking]) -> Result<(), String> {
+        let days = serialize_rows(days, |day| day.date)?;
+        let bookings = serialize_rows(bookings, |booking| booking.date)?;
+        let mut connection =
+            self.connect(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+        let write = |connection: &mut Connection| {
+            let transaction = connection.transaction().map_err(|err| err.to_string())?;
+            Self::check_version(&transaction)?;
+            let sync = || {
+                Self::create_tables(&transaction)?;
+                Self::sync(&transaction, "days", "day", days)?;
+                Self::sync(&transaction, "bookings", "booking", bookings)
+            };
+            sync().map_err(|err| err.to_string())?;
+            transaction.commit().map_err(|err| err.to_string())
+        };
+        write(&mut connection)
+            .map_err(|err| format!("Could not write database {}: {err}", self.file_path))?;
+        drop(connection);
+        self.fingerprint = fingerprint(&self.file_path)?;
+        Ok(())
+    }
+
+    fn upgrade(&mut self) -> Result<Option<(u32, u32)>, String> {
+        // the first layout of the database is still the current one, its days are stored in the
+        // current collection format
+        Ok(None)
+    }
+
+    fn changed(&self) -> Result<bool, String> {
+        Ok(fingerprint(&self.file_path)? != self.fingerprint)
+    }
+}
@@ -0,0 +1,237 @@
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::stat::has_target;
+use crate::work_day::WorkDay;
+
+/// Day of the year without a year, as `MM-DD`
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct MonthDay {
+    pub month: u32,
+    pub day: u32,
+}
+
+impl MonthDay {
+    /// The date in `year`, the 29th of february is the 28th in common years
+    pub fn in_year(&self, year: i32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, self.month, self.day)
+            .or_else(|| NaiveDate::from_ymd_opt(year, self.month, self.day - 1))
+            .unwrap()
+    }
+}
+
+impl TryFrom<String> for MonthDay {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("expected a date as MM-DD, got {text}");
+        let (month, day) = text.trim().split_once('-').ok_or_else(invalid)?;
+        let month = month.parse().map_err(|_| invalid())?;
+        let day = day.parse().map_err(|_| invalid())?;
+        // 2000 is a leap year, so the 29th of february is valid
+        NaiveDate::from_ymd_opt(2000, month, day).ok_or_else(invalid)?;
+        Ok(Self { month, day })
+    }
+}
+
+impl From<MonthDay> for String {
+    fn from(month_day: MonthDay) -> Self {
+        format!("{:02}-{:02}", month_day.month, month_day.day)
+    }
+}
+
+/// Yearly vacation allowance
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Entitlement {
+    /// Vacation days per year
+    pub days: f32,
+    /// Days carried over from the previous year expire if they are not taken until this day
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub carry_over_expires: Option<MonthDay>,
+}
+
+impl Entitlement {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=366.0).contains(&self.days) {
+            return Err(format!("invalid vacation days {}", self.days));
+        }
+        Ok(())
+    }
+}
+
+/// Vacation account of a single year
+#[derive(Debug, Clone, PartialEq)]
+pub struct VacationYear {
+    pub year: i32,
+    pub entitlement: f32,
+    /// Days left over from the previous year, negative if it was overdrawn
+    pub carried: f32,
+    /// Carried days that were not taken until the expiry date
+    pub expired: f32,
+    /// Vacation days up to today
+    pub taken: f32,
+    /// Vacation days after today
+    pub planned: f32,
+}
+
+impl VacationYear {
+    pub fn remaining(&self) -> f32 {
+        self.entitlement + self.carried - self.expired - self.taken - self.planned
+    }
+}
+
+/// Vacation account of `year`, starting with the year of the first entry in `days` that is not a
+/// holiday, see [`crate::stat::has_target`]. Full
+/// vacation days count as one day, partial vacation with its share. Entries after `today` are
+/// planned.
+pub fn vacation_year(
+    days: &[WorkDay],
+    entitlement: &Entitlement,
+    year: i32,
+    today: NaiveDate,
+) -> VacationYear {
+    let first_year = days
+        .iter()
+        .filter(|day| has_target(day))
+        .map(|day| day.date.year())
+        .min()
+        .unwrap_or(year)
+        .min(year);
+
+    let mut carried = 0.0;
+    let mut account = None;
+    for current in first_year..=year {
+        // vacation of the current year on the days matching `filter`
+        let share = |filter: &dyn Fn(&WorkDay) -> bool| {
+            days.iter()
+                .filter(|day| day.date.year() == current && filter(day))
+                .map(WorkDay::vacation_share)
+                // `sum` of no days would be -0, which is shown with its sign
+                .fold(0.0, |acc, share| acc + share)
+        };
+
+        let expired = match entitlement.carry_over_expires {
+            Some(expires) if carried > 0.0 && today > expires.in_year(current) => {
+                let expires = expires.in_year(current);
+                (carried - share(&|day| day.date <= expires)).max(0.0)
+            }
+            _ => 0.0,
+        };
+        let year_account = VacationYear {
+            year: current,
+            entitlement: entitlement.days,
+            carried,
+            expired,
+            taken: share(&|day| day.date <= today),
+            planned: share(&|day| day.date > today),
+        };
+        carried = year_account.remaining();
+        account = Some(year_account);
+    }
+    account.unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::work_day::{Absence, AbsenceKind, DayType};
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    /// `count` vacation days in a row from `first`
+    fn vacation(first: NaiveDate, count: i64) -> Vec<WorkDay> {
+        (0..count)
+            .map(|i| WorkDay::new(first + Duration::days(i), DayType::Vacation))
+            .collect()
+    }
+
+    fn entitlement(carry_over_expires: Option<&str>) -> Entitlement {
+        Entitlement {
+            days: 30.0,
+            carry_over_expires: carry_over_expires
+                .map(|text| MonthDay::try_from(String::from(text)).unwrap()),
+        }
+    }
+
+    #[test]
+    fn without_entries() {
+        let account = vacation_year(&[], &entitlement(None), 2025, date(2025, 6, 1));
+        assert_eq!(account.carried, 0.0);
+        assert!(account.taken.is_sign_positive() && account.planned.is_sign_positive());
+        assert_eq!(account.remaining(), 30.0);
+    }
+
+    #[test]
+    fn carry_over() {
+        let mut days = vacation(date(2023, 8, 1), 32);
+        days.extend(vacation(date(2024, 8, 1), 20));
+        let today = date(2025, 6, 1);
+
+        let overdrawn = vacation_year(&days, &entitlement(None), 2024, today);
+        assert_eq!(overdrawn.carried, -2.0);
+        assert_eq!(overdrawn.remaining(), 8.0);
+        let account = vacation_year(&days, &entitlement(None), 2025, today);
+        assert_eq!(account.carried, 8.0);
+        assert_eq!(account.remaining(), 38.0);
+    }
+
+    #[test]
+    fn holidays_do_not_start_the_account() {
+        let mut days = vacation(date(2025, 3, 3), 1);
+        days.push(WorkDay::new(
+            date(2024, 12, 25),
+            DayType::Holiday {
+                name: String::from("1. Weihnachtstag"),
+            },
+        ));
+        let account = vacation_year(&days, &entitlement(None), 2025, date(2025, 6, 1));
+        assert_eq!(account.carried, 0.0);
+        assert_eq!(account.remaining(), 29.0);
+    }
+
+    #[test]
+    fn expiry() {
+        let mut days = vacation(date(2024, 8, 1), 25);
+        days.extend(vacation(date(2025, 3, 28), 2));
+        let expires = entitlement(Some("03-31"));
+
+        // the carried days can still be taken until the end of march
+        let before = vacation_year(&days, &expires, 2025, date(2025, 3, 31));
+        assert_eq!((before.carried, before.expired), (5.0, 0.0));
+        let after = vacation_year(&days, &expires, 2025, date(2025, 4, 1));
+        assert_eq!((after.carried, after.expired), (5.0, 3.0));
+        assert_eq!(after.remaining(), 30.0);
+        // days after the expiry count against the entitlement of the year
+        days.extend(vacation(date(2025, 4, 1), 1));
+        let later = vacation_year(&days, &expires, 2025, date(2025, 4, 2));
+        assert_eq!((later.expired, later.remaining()), (3.0, 29.0));
+    }
+
+    #[test]
+    fn planned_after_today() {
+        let mut days = vacation(date(2025, 5, 30), 3);
+        days.push(WorkDay {
+            absence: Some(Absence {
+                kind: AbsenceKind::Vacation,
+                share: 0.5,
+            }),
+            ..WorkDay::new(
+                date(2025, 7, 1),
+                DayType::Present {
+                    sessions: Vec::new(),
+                    breaks: Vec::new(),
+                },
+            )
+        });
+        let account = vacation_year(&days, &entitlement(None), 2025, date(2025, 5, 31));
+        assert_eq!((account.taken, account.planned), (2.0, 1.5));
+        assert_eq!(account.remaining(), 26.5);
+    }
+}
@@ -1,50 +1,379 @@
+use std::collections::{BTreeMap, BTreeSet};
+
 use chrono::{Duration, NaiveDate, NaiveTime};
 use serde::{self, Deserialize, Serialize};
 
 use crate::disp_utils::hm_from_duration;
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
-pub struct Break {
-    #[serde(rename = "break_start")]
+/// A span of time within a day, used for work sessions as well as breaks
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Interval {
     pub start: NaiveTime,
-    #[serde(rename = "break_end")]
-    pub end: NaiveTime,
+    /// `None` while the interval is still running
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<NaiveTime>,
+    /// Whether `end` falls on the next calendar day, e.g. for overnight shifts
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub next_day: bool,
+    /// Whether `start` falls on the next calendar day as well, e.g. for a break after midnight in
+    /// an overnight shift
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub start_next_day: bool,
+}
+
+impl Interval {
+    /// Duration of the interval, running intervals have not accumulated any time yet
+    pub fn duration(&self) -> Duration {
+        self.end.map_or(Duration::zero(), |end| {
+            end - self.start + self.day_offset() - self.start_offset()
+        })
+    }
+
+    /// Offset of `start` relative to the day of the entry
+    pub fn start_offset(&self) -> Duration {
+        if self.start_next_day {
+            Duration::days(1)
+        } else {
+            Duration::zero()
+        }
+    }
+
+    /// Start and end as offsets from the start of the day of the entry, more than a day if they
+    /// are on the next day
+    pub fn offsets(&self) -> (Duration, Option<Duration>) {
+        (
+            self.start - NaiveTime::MIN + self.start_offset(),
+            self.end.map(|end| end - NaiveTime::MIN + self.day_offset()),
+        )
+    }
+
+    /// Start as `HH:MM`, with a `+1` suffix if it is on the next day
+    pub fn start_text(&self) -> String {
+        let next_day = if self.start_next_day { "+1" } else { "" };
+        format!("{}{next_day}", self.start.format("%H:%M"))
+    }
+
+    /// Offset of `end` relative to the day the interval starts on
+    pub fn day_offset(&self) -> Duration {
+        if self.next_day {
+            Duration::days(1)
+        } else {
+            Duration::zero()
+        }
+    }
+
+    /// Checks that the interval does not end before it starts and lasts less than a day
+    pub fn validate(&self, name: &str) -> Result<(), String> {
+        match self.end {
+            Some(_) if self.start_next_day && !self.next_day => Err(format!(
+                "{name} starts on the next day, mark the end with +1 as well"
+            )),
+            Some(end) if self.next_day == self.start_next_day && end < self.start => Err(format!(
+                "{name} ends before it starts, mark the end with +1 if it is on the next day"
+            )),
+            Some(end) if self.next_day && !self.start_next_day && end >= self.start => {
+                Err(format!("{name} is longer than a day"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::str::FromStr for Interval {
+    type Err = String;
+
+    /// Parses a finished interval `HH:MM-HH:MM`, with a `+1` suffix for a start or end on the
+    /// next day
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .trim()
+            .split_once('-')
+            .ok_or(format!("expected an interval as HH:MM-HH:MM, got {s}"))?;
+        let (start, start_next_day) = match start.strip_suffix("+1") {
+            Some(start) => (start, true),
+            None => (start, false),
+        };
+        let (end, next_day) = match end.strip_suffix("+1") {
+            Some(end) => (end, true),
+            None => (end, false),
+        };
+        let interval = Interval {
+            start: NaiveTime::parse_from_str(start, "%H:%M")
+                .map_err(|err| format!("invalid start {start}: {err}"))?,
+            end: Some(
+                NaiveTime::parse_from_str(end, "%H:%M")
+                    .map_err(|err| format!("invalid end {end}: {err}"))?,
+            ),
+            next_day,
+            start_next_day,
+        };
+        interval.validate(s)?;
+        Ok(interval)
+    }
+}
+
+impl std::fmt::Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.end {
+            Some(end) => write!(
+                f,
+                "{}-{}{}",
+                self.start_text(),
+                end.format("%H:%M"),
+                if self.next_day { "+1" } else { "" }
+            ),
+            None => write!(f, "{}-running", self.start_text()),
+        }
+    }
+}
+
+/// Parses a duration given as `HH:MM`
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let (hours, minutes) = text
+        .trim()
+        .split_once(':')
+        .ok_or(format!("expected a duration as HH:MM, got {text}"))?;
+    let hours = hours
+        .parse::<u32>()
+        .map_err(|err| format!("invalid hours in duration {text}: {err}"))?;
+    let minutes = minutes
+        .parse::<u32>()
+        .map_err(|err| format!("invalid minutes in duration {text}: {err}"))?;
+    if minutes >= 60 {
+        return Err(format!("invalid minutes in duration {text}"));
+    }
+    Ok(Duration::minutes((hours * 60 + minutes) as i64))
+}
+
+/// (De)serializes a duration as `HH:MM`
+mod hm_format {
+    use chrono::Duration;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::disp_utils::hm_from_duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hm_from_duration(*duration))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        super::parse_duration(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Worked time booked on a project, either as a plain duration or as a time interval
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(untagged)]
+pub enum Allocation {
+    Duration {
+        project: String,
+        #[serde(with = "hm_format")]
+        duration: Duration,
+    },
+    Interval {
+        project: String,
+        #[serde(flatten)]
+        interval: Interval,
+    },
+}
+
+impl Allocation {
+    pub fn project(&self) -> &str {
+        match self {
+            Allocation::Duration { project, .. } | Allocation::Interval { project, .. } => project,
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        match self {
+            Allocation::Duration { duration, .. } => *duration,
+            Allocation::Interval { interval, .. } => interval.duration(),
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Worked {
+/// A single break in the flattened `break_start` / `break_end` format of older collections
+#[derive(Debug, Deserialize)]
+struct LegacyBreak {
+    break_start: NaiveTime,
+    #[serde(default)]
+    break_end: Option<NaiveTime>,
+}
+
+/// A single work session in the flattened `start` / `end` format of older collections
+#[derive(Debug, Deserialize)]
+struct LegacySession {
     start: NaiveTime,
-    end: NaiveTime,
+    #[serde(default)]
+    end: Option<NaiveTime>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(tag = "info")]
 #[serde(rename_all = "lowercase")]
+#[serde(from = "DayTypeRepr")]
 pub enum DayType {
     Present {
-        start: NaiveTime,
-        end: NaiveTime,
+        sessions: Vec<Interval>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        breaks: Vec<Interval>,
+    },
+    HomeOffice {
+        sessions: Vec<Interval>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        breaks: Vec<Interval>,
+    },
+    Unofficial {
+        sessions: Vec<Interval>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        breaks: Vec<Interval>,
+    },
+    Travel {
+        sessions: Vec<Interval>,
+    },
+    Sick,
+    Vacation,
+    /// Day off to reduce overtime, it is not credited with the daily target
+    Compensation,
+    /// Public holiday, see [`crate::holidays`]
+    Holiday {
+        #[serde(default)]
+        #[serde(skip_serializing_if = "String::is_empty")]
+        name: String,
+    },
+}
+
+/// On-disk representation of [`DayType`], which also accepts the formats of older collections
+#[derive(Deserialize)]
+#[serde(tag = "info")]
+#[serde(rename_all = "lowercase")]
+enum DayTypeRepr {
+    Present {
+        #[serde(default)]
+        sessions: Vec<Interval>,
+        #[serde(flatten)]
+        legacy_session: Option<LegacySession>,
+        #[serde(default)]
+        breaks: Vec<Interval>,
         #[serde(flatten)]
-        brk: Break,
+        legacy_break: Option<LegacyBreak>,
     },
     HomeOffice {
-        start: NaiveTime,
-        end: NaiveTime,
+        #[serde(default)]
+        sessions: Vec<Interval>,
         #[serde(flatten)]
-        brk: Break,
+        legacy_session: Option<LegacySession>,
+        #[serde(default)]
+        breaks: Vec<Interval>,
+        #[serde(flatten)]
+        legacy_break: Option<LegacyBreak>,
     },
     Unofficial {
-        start: NaiveTime,
-        end: NaiveTime,
+        #[serde(default)]
+        sessions: Vec<Interval>,
+        #[serde(flatten)]
+        legacy_session: Option<LegacySession>,
+        #[serde(default)]
+        breaks: Vec<Interval>,
         #[serde(flatten)]
-        brk: Option<Break>,
+        legacy_break: Option<LegacyBreak>,
     },
     Travel {
-        start: NaiveTime,
-        end: NaiveTime,
+        #[serde(default)]
+        sessions: Vec<Interval>,
+        #[serde(flatten)]
+        legacy_session: Option<LegacySession>,
     },
     Sick,
     Vacation,
+    Compensation,
+    Holiday {
+        #[serde(default)]
+        name: String,
+    },
+}
+
+fn with_legacy_session(
+    mut sessions: Vec<Interval>,
+    legacy_session: Option<LegacySession>,
+) -> Vec<Interval> {
+    if let Some(session) = legacy_session {
+        sessions.insert(
+            0,
+            Interval {
+                start: session.start,
+                end: session.end,
+                next_day: false,
+                start_next_day: false,
+            },
+        );
+    }
+    sessions
+}
+
+fn with_legacy_break(
+    mut breaks: Vec<Interval>,
+    legacy_break: Option<LegacyBreak>,
+) -> Vec<Interval> {
+    if let Some(brk) = legacy_break {
+        breaks.insert(
+            0,
+            Interval {
+                start: brk.break_start,
+                end: brk.break_end,
+                next_day: false,
+                start_next_day: false,
+            },
+        );
+    }
+    breaks
+}
+
+impl From<DayTypeRepr> for DayType {
+    fn from(repr: DayTypeRepr) -> Self {
+        match repr {
+            DayTypeRepr::Present {
+                sessions,
+                legacy_session,
+                breaks,
+                legacy_break,
+            } => DayType::Present {
+                sessions: with_legacy_session(sessions, legacy_session),
+                breaks: with_legacy_break(breaks, legacy_break),
+            },
+            DayTypeRepr::HomeOffice {
+                sessions,
+                legacy_session,
+                breaks,
+                legacy_break,
+            } => DayType::HomeOffice {
+                sessions: with_legacy_session(sessions, legacy_session),
+                breaks: with_legacy_break(breaks, legacy_break),
+            },
+            DayTypeRepr::Unofficial {
+                sessions,
+                legacy_session,
+                breaks,
+                legacy_break,
+            } => DayType::Unofficial {
+                sessions: with_legacy_session(sessions, legacy_session),
+                breaks: with_legacy_break(breaks, legacy_break),
+            },
+            DayTypeRepr::Travel {
+                sessions,
+                legacy_session,
+            } => DayType::Travel {
+                sessions: with_legacy_session(sessions, legacy_session),
+            },
+            DayTypeRepr::Sick => DayType::Sick,
+            DayTypeRepr::Vacation => DayType::Vacation,
+            DayTypeRepr::Compensation => DayType::Compensation,
+            DayTypeRepr::Holiday { name } => DayType::Holiday { name },
+        }
+    }
 }
 
 impl Default for DayType {
@@ -53,91 +382,401 @@ impl Default for DayType {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl DayType {
+    pub fn sessions(&self) -> &[Interval] {
+        match self {
+            DayType::Present { sessions, .. }
+            | DayType::HomeOffice { sessions, .. }
+            | DayType::Unofficial { sessions, .. }
+            | DayType::Travel { sessions } => sessions,
+            DayType::Vacation | DayType::Sick | DayType::Compensation | DayType::Holiday { .. } => {
+                &[]
+            }
+        }
+    }
+
+    pub fn breaks(&self) -> &[Interval] {
+        match self {
+            DayType::Present { breaks, .. }
+            | DayType::HomeOffice { breaks, .. }
+            | DayType::Unofficial { breaks, .. } => breaks,
+            DayType::Travel { .. }
+            | DayType::Vacation
+            | DayType::Sick
+            | DayType::Compensation
+            | DayType::Holiday { .. } => &[],
+        }
+    }
+
+    /// Whether breaks can be entered on the day type, travel days have none
+    pub fn has_breaks(&self) -> bool {
+        matches!(
+            self,
+            DayType::Present { .. } | DayType::HomeOffice { .. } | DayType::Unofficial { .. }
+        )
+    }
+}
+
+/// Kind of a partial absence on a worked day
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AbsenceKind {
+    Vacation,
+    Sick,
+    Compensation,
+}
+
+impl std::fmt::Display for AbsenceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AbsenceKind::Vacation => write!(f, "Vacation"),
+            AbsenceKind::Sick => write!(f, "Sick"),
+            AbsenceKind::Compensation => write!(f, "Compensation"),
+        }
+    }
+}
+
+/// Part of a worked day spent on vacation or sick leave, e.g. a vacation morning followed by a
+/// worked afternoon
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Absence {
+    pub kind: AbsenceKind,
+    /// Share of the daily target covered by the absence, between 0 and 1
+    pub share: f32,
+}
+
+impl Absence {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.share > 0.0 && self.share < 1.0 {
+            Ok(())
+        } else {
+            Err(String::from(
+                "absence share must be between 0 and 1, use a day type of its own for a full day",
+            ))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct WorkDay {
     pub date: NaiveDate,
     #[serde(default)]
     #[serde(flatten)]
     pub day_type: DayType,
+    /// Partial absence on a worked day, full days of absence are a [`DayType`] of their own
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub absence: Option<Absence>,
+    /// Free text note, e.g. "doctor at 10"
+    #[serde(default)]
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub note: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+    /// Worked time booked on projects
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allocations: Vec<Allocation>,
+}
+
+/// Parses a list of tags separated by commas or whitespace, a leading `#` is optional
+pub fn parse_tags(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .map(|tag| tag.trim_start_matches('#'))
+        .filter(|tag| !tag.is_empty())
+        .map(String::from)
+        .collect()
 }
 
 impl WorkDay {
-    pub fn to_string(&self) -> String {
-        match self.day_type {
-            DayType::Present { start, end, .. } => {
-                format!(
-                    "{date} -> {:11}  {start} - {end} ({time}h)",
-                    "Present",
-                    start = start.format("%H:%M"),
-                    end = end.format("%H:%M"),
-                    date = self.date.format("%d.%m.%y"),
-                    time = hm_from_duration(self.worked_time()),
-                )
-            }
-            DayType::HomeOffice { start, end, .. } => {
-                format!(
-                    "{date} -> {:11}  {start} - {end} ({time}h)",
-                    "Home Office",
-                    start = start.format("%H:%M"),
-                    end = end.format("%H:%M"),
-                    date = self.date.format("%d.%m.%y"),
-                    time = hm_from_duration(self.worked_time()),
-                )
-            }
-            DayType::Unofficial { start, end, .. } => {
-                format!(
-                    "{date} -> {:11}  {start} - {end} ({time}h)",
-                    "Unofficial",
-                    start = start.format("%H:%M"),
-                    end = end.format("%H:%M"),
-                    date = self.date.format("%d.%m.%y"),
-                    time = hm_from_duration(self.worked_time()),
-                )
-            }
+    /// Entry of `date` without an absence, note, tags or allocations
+    pub fn new(date: NaiveDate, day_type: DayType) -> Self {
+        Self {
+            date,
+            day_type,
+            absence: None,
+            note: String::new(),
+            tags: BTreeSet::new(),
+            allocations: Vec::new(),
+        }
+    }
+
+    /// One line summary of the day with tags and note, dates are formatted with `date_format`
+    pub fn display(&self, date_format: &str) -> String {
+        let mut ret = self.summary(date_format);
+        if !self.tags.is_empty() {
+            ret.push_str(&format!(
+                "  [{}]",
+                self.tags.iter().cloned().collect::<Vec<_>>().join(", ")
+            ));
+        }
+        if !self.note.is_empty() {
+            ret.push_str(&format!("  {}", self.note));
+        }
+        ret
+    }
+
+    /// Date, day type and times of the day, without tags and note
+    fn summary(&self, date_format: &str) -> String {
+        let name = match &self.day_type {
+            DayType::Present { .. } => "Present",
+            DayType::HomeOffice { .. } => "Home Office",
+            DayType::Unofficial { .. } => "Unofficial",
+            DayType::Travel { .. } => "Travel",
             DayType::Sick => {
-                format!("{date} -> Sick", date = self.date.format("%d.%m.%y"))
-            }
-            DayType::Travel { start, end } => {
-                format!(
-                    "{date} -> {:11}  {start} - {end} ({time}h)",
-                    "Travel",
-                    start = start.format("%H:%M"),
-                    end = end.format("%H:%M"),
-                    date = self.date.format("%d.%m.%y"),
-                    time = hm_from_duration(self.worked_time()),
-                )
+                return format!("{date} -> Sick", date = self.date.format(date_format));
             }
             DayType::Vacation => {
-                format!("{date} -> Vacation", date = self.date.format("%d.%m.%y"))
+                return format!("{date} -> Vacation", date = self.date.format(date_format));
             }
+            DayType::Compensation => {
+                return format!(
+                    "{date} -> Compensation",
+                    date = self.date.format(date_format)
+                );
+            }
+            DayType::Holiday { name } => {
+                return format!(
+                    "{date} -> {holiday:11}  {name}",
+                    date = self.date.format(date_format),
+                    holiday = "Holiday"
+                );
+            }
+        };
+
+        format!(
+            "{date} -> {name:11}  {sessions} ({time}h){on_break}{absence}",
+            date = self.date.format(date_format),
+            sessions = self
+                .sessions()
+                .iter()
+                .map(Interval::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+            time = hm_from_duration(self.worked_time()),
+            on_break = if self.breaks().iter().any(|brk| brk.end.is_none()) {
+                " on break"
+            } else {
+                ""
+            },
+            absence = match self.absence {
+                Some(Absence { kind, share }) => format!(" + {share} {kind}"),
+                None => String::new(),
+            },
+        )
+    }
+
+    /// Share of the day spent on vacation, 1 for a full vacation day
+    pub fn vacation_share(&self) -> f32 {
+        match (&self.day_type, self.absence) {
+            (DayType::Vacation, _) => 1.0,
+            (
+                _,
+                Some(Absence {
+                    kind: AbsenceKind::Vacation,
+                    share,
+                }),
+            ) => share,
+            _ => 0.0,
         }
     }
 
+    /// Share of the day spent on sick leave, 1 for a full sick day
+    pub fn sick_share(&self) -> f32 {
+        match (&self.day_type, self.absence) {
+            (DayType::Sick, _) => 1.0,
+            (
+                _,
+                Some(Absence {
+                    kind: AbsenceKind::Sick,
+                    share,
+                }),
+            ) => share,
+            _ => 0.0,
+        }
+    }
+
+    /// Share of the day taken off to reduce overtime, 1 for a full compensation day
+    pub fn compensation_share(&self) -> f32 {
+        match (&self.day_type, self.absence) {
+            (DayType::Compensation, _) => 1.0,
+            (
+                _,
+                Some(Absence {
+                    kind: AbsenceKind::Compensation,
+                    share,
+                }),
+            ) => share,
+            _ => 0.0,
+        }
+    }
+
+    /// Worked time of the day, summed over all work sessions without breaks.
+    /// Sessions and breaks that are still running have not accumulated any time yet.
+    /// Overnight sessions are attributed to the day they start on.
     pub fn worked_time(&self) -> Duration {
-        match &self.day_type {
-            DayType::Present { start, end, brk } => *end - *start - (brk.end - brk.start),
-            DayType::HomeOffice { start, end, brk } => *end - *start - (brk.end - brk.start),
-            DayType::Sick => Duration::zero(),
-            DayType::Unofficial { start, end, brk } => {
-                *end - *start
-                    - brk
-                        .as_ref()
-                        .map_or(Duration::zero(), |brk| brk.end - brk.start)
-            }
-            DayType::Travel { start, end } => *end - *start,
-            DayType::Vacation => Duration::zero(),
+        self.sessions()
+            .iter()
+            .fold(Duration::zero(), |acc, session| acc + session.duration())
+            - self.break_time()
+    }
+
+    /// Worked time booked on each project
+    pub fn project_times(&self) -> BTreeMap<String, Duration> {
+        let mut ret = BTreeMap::new();
+        for allocation in &self.allocations {
+            let time = ret
+                .entry(String::from(allocation.project()))
+                .or_insert(Duration::zero());
+            *time = *time + allocation.duration();
         }
+        ret
+    }
+
+    /// Worked time not booked on any project, negative if more time is booked than worked
+    pub fn unallocated_time(&self) -> Duration {
+        self.allocations
+            .iter()
+            .fold(self.worked_time(), |acc, allocation| {
+                acc - allocation.duration()
+            })
     }
 
     pub fn break_time(&self) -> Duration {
-        match &self.day_type {
-            DayType::Present { brk, .. }
-            | DayType::HomeOffice { brk, .. }
-            | DayType::Unofficial { brk: Some(brk), .. } => brk.end - brk.start,
-            DayType::Travel { .. }
-            | DayType::Vacation
-            | DayType::Sick
-            | DayType::Unofficial { brk: None, .. } => Duration::zero(),
+        self.breaks()
+            .iter()
+            .fold(Duration::zero(), |acc, brk| acc + brk.duration())
+    }
+
+    pub fn sessions(&self) -> &[Interval] {
+        self.day_type.sessions()
+    }
+
+    pub fn breaks(&self) -> &[Interval] {
+        self.day_type.breaks()
+    }
+
+    /// Start of the first work session
+    pub fn start(&self) -> Option<NaiveTime> {
+        self.sessions().first().map(|session| session.start)
+    }
+
+    /// End of the last work session, `None` if there is none or it is still running
+    pub fn end(&self) -> Option<NaiveTime> {
+        self.sessions().last().and_then(|session| session.end)
+    }
+
+    /// End of the last work session as an offset from the start of the day, more than a day for
+    /// an end on the next day
+    pub fn end_offset(&self) -> Option<Duration> {
+        self.sessions()
+            .last()
+            .and_then(|session| session.offsets().1)
+    }
+
+    /// Whether a work session has been started but not yet stopped
+    pub fn is_running(&self) -> bool {
+        self.sessions().iter().any(|session| session.end.is_none())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: u32, min: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, min, 0).unwrap()
+    }
+
+    fn interval(start: NaiveTime, end: Option<NaiveTime>) -> Interval {
+        Interval {
+            start,
+            end,
+            next_day: false,
+            start_next_day: false,
         }
     }
+
+    #[test]
+    fn legacy_session_and_break() {
+        let day: WorkDay = serde_json::from_str(
+            r#"{"date": "2023-05-02", "info": "homeoffice", "start": "08:00:00",
+                "end": "16:30:00", "break_start": "12:00:00", "break_end": "12:30:00"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            day.day_type,
+            DayType::HomeOffice {
+                sessions: vec![interval(time(8, 0), Some(time(16, 30)))],
+                breaks: vec![interval(time(12, 0), Some(time(12, 30)))],
+            }
+        );
+    }
+
+    #[test]
+    fn legacy_running_session_without_break() {
+        let day: WorkDay = serde_json::from_str(
+            r#"{"date": "2023-05-02", "info": "present", "start": "08:00:00"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            day.day_type,
+            DayType::Present {
+                sessions: vec![interval(time(8, 0), None)],
+                breaks: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn legacy_travel_and_absence_days() {
+        let days: Vec<WorkDay> = serde_json::from_str(
+            r#"[{"date": "2023-05-02", "info": "travel", "start": "06:00:00", "end": "20:00:00"},
+                {"date": "2023-05-03", "info": "vacation"},
+                {"date": "2023-05-04", "info": "holiday"}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            days[0].day_type,
+            DayType::Travel {
+                sessions: vec![interval(time(6, 0), Some(time(20, 0)))],
+            }
+        );
+        assert_eq!(days[1].day_type, DayType::Vacation);
+        assert_eq!(
+            days[2].day_type,
+            DayType::Holiday {
+                name: String::new()
+            }
+        );
+    }
+
+    #[test]
+    fn current_format_round_trip() {
+        let day = WorkDay {
+            note: String::from("night shift"),
+            tags: parse_tags("release, oncall"),
+            ..WorkDay::new(
+                NaiveDate::from_ymd_opt(2023, 5, 2).unwrap(),
+                DayType::Unofficial {
+                    sessions: vec![
+                        interval(time(8, 0), Some(time(12, 0))),
+                        Interval {
+                            next_day: true,
+                            ..interval(time(22, 0), Some(time(2, 0)))
+                        },
+                    ],
+                    breaks: vec![Interval {
+                        next_day: true,
+                        start_next_day: true,
+                        ..interval(time(0, 30), Some(time(1, 0)))
+                    }],
+                },
+            )
+        };
+        let json = serde_json::to_string(&day).unwrap();
+        assert!(!json.contains("break_start"));
+        assert_eq!(serde_json::from_str::<WorkDay>(&json).unwrap(), day);
+    }
 }